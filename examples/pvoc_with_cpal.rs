@@ -11,9 +11,10 @@ use ringbuf::RingBuffer;
 
 use pvoc::{Bin, PhaseVocoder};
 use pvoc_mini_plugins::{
-    AmpDelay, BinFlipper, Centroid, DomainXOver, ExpAvg, FormantShifter, FreqShifter, Gate,
-    ModularAmp, PVocMiniPlugin, PitchShifter, Repeater, Scrambler, SlopeFilter, Stencil, Through,
-    TimeBlur,
+    AmpDelay, BinCurve, BinFlipper, Centroid, DomainXOver, EqBand, ExpAvg, FormantShifter,
+    FreqShifter, Gate, Harmonizer, MidSide, ModularAmp, PVocMiniPlugin, PanMode, PitchCorrector,
+    PitchShifter, Repeater, Robotize, Scale, Scrambler, SlopeFilter, SpectralDelay, SpectralEq,
+    SpectralPanner, Stencil, Through, TimeBlur, TransientSplitter, Voice, Whisperize,
 };
 
 #[derive(Parser, Debug)]
@@ -138,25 +139,68 @@ fn main() -> Result<()> {
             &mut output,
             |channels: usize, bins: usize, input: &[Vec<Bin>], output: &mut [Vec<Bin>]| {
                 let mut plugin: Box<dyn PVocMiniPlugin> = match opt.plugin.as_str() {
-                    "AmpDelay" => {
-                        Box::new(AmpDelay::new(channels, bins, 1.0, 1, 1.0, 1.0, 1.0, 1.0))
-                    }
+                    "AmpDelay" => Box::new(AmpDelay::new(
+                        channels, bins, time_div, 1.0, 1, 1.0, 1.0, 1.0, 1.0,
+                    )),
                     "BinFlipper" => Box::new(BinFlipper::new(0.01)),
                     "Centroid" => Box::new(Centroid::new()),
                     "DomainXOver" => Box::new(DomainXOver::new(15.0, 0.5, 0.5)),
                     "ExpAvg" => Box::new(ExpAvg::new(0.8, 0.2, 0.3, 0.7)),
                     "FormantShifter" => Box::new(FormantShifter::new(0.0)),
                     "FreqShifter" => Box::new(FreqShifter::new(8.0)),
-                    "Gate" => Box::new(Gate::new(0.5, 7.0)),
+                    "Gate" => Box::new(Gate::new(channels, bins, time_div, -60.0, 0.0)),
+                    "Harmonizer" => Box::new(Harmonizer::new(vec![
+                        Voice::new(0.0, 1.0, 0.0),
+                        Voice::new(4.0, 0.7, -0.5),
+                        Voice::new(7.0, 0.7, 0.5),
+                    ])),
+                    "MidSide" => Box::new(MidSide::new(
+                        bins,
+                        Through::new(),
+                        Gate::new(1, bins, time_div, -50.0, 0.0),
+                    )),
                     "ModularAmp" => Box::new(ModularAmp::new(12.5)),
+                    "PitchCorrector" => {
+                        Box::new(PitchCorrector::new(channels, 0, Scale::Major, 0.5))
+                    }
                     "PitchShifter" => Box::new(PitchShifter::new(8.0)),
-                    "Repeater" => Box::new(Repeater::new(channels, bins, 10, 0.5, 0.2, 0.1, 0.5)),
-                    "Scrambler" => Box::new(Scrambler::new(channels, bins, 10, 1)),
-                    "SlopeFilter" => Box::new(SlopeFilter::new(channels, bins, 0.1, 0.8, 0.1, 0.8)),
+                    "Repeater" => Box::new(Repeater::new(
+                        channels, bins, time_div, 10, 0.5, 0.2, 0.1, 0.5,
+                    )),
+                    "Robotize" => Box::new(Robotize::new(110.0, 1.0)),
+                    "Scrambler" => Box::new(Scrambler::new(channels, bins, time_div, 10, 1)),
+                    "SlopeFilter" => {
+                        Box::new(SlopeFilter::new(channels, bins, 0.0, 50.0, 0.0, 6.0))
+                    }
+                    "SpectralDelay" => {
+                        let mut delay = SpectralDelay::new(channels, bins, 200.0, 0.6, 0.5);
+                        delay.delay_curve = BinCurve::Falling;
+                        delay.feedback_plugin = Some(Box::new(FreqShifter::new(2.0)));
+                        Box::new(delay)
+                    }
+                    "SpectralEq" => Box::new(SpectralEq::new(
+                        vec![
+                            EqBand::LowShelf {
+                                freq: 200.0,
+                                gain: -12.0,
+                            },
+                            EqBand::Bell {
+                                freq: 3000.0,
+                                gain: 6.0,
+                                width: 1.0,
+                            },
+                        ],
+                        0.5,
+                    )),
+                    "SpectralPanner" => Box::new(SpectralPanner::new(PanMode::Random(0), 1.0, 0.0)),
                     "Stencil" => Box::new(Stencil::new(channels, bins, 100)),
-                    "TimeBlur" => {
-                        Box::new(TimeBlur::new(channels, bins, 0.5, 0.5, 0.5, 1.0, 0.8, 0.2))
+                    "TimeBlur" => Box::new(TimeBlur::new(
+                        channels, bins, time_div, 0.5, 0.5, 0.5, 1.0, 0.8, 0.2,
+                    )),
+                    "TransientSplitter" => {
+                        Box::new(TransientSplitter::new(channels, bins, 17, 17, 2.0))
                     }
+                    "Whisperize" => Box::new(Whisperize::new(1.0, 0)),
                     _ => Box::new(Through::new()),
                 };
                 plugin.process(sample_rate, channels, bins, input, output);
@@ -1,30 +1,59 @@
+use super::bin_freq;
 use super::lerp;
+use super::sample_curve;
+use super::ChannelParam;
 use super::PVocMiniPlugin;
+use super::TempoSync;
 use pvoc::Bin;
 
+/// What the delay of each bin of the [`AmpDelay`] is relative to.
+///
+/// - Amplitude: log2 of the amplitude plus one
+/// - Frequency: frequency of the bin relative to nyquist [0.0, 1.0]
+/// - BinIndex: position of the bin between the lowest bin and nyquist [0.0, 1.0]
+/// - Curve: values [0.0, 1.0] spread evenly between the lowest bin and nyquist, interpolated in between
+#[derive(Clone, Debug, PartialEq)]
+pub enum DelayMapping {
+    Amplitude,
+    Frequency,
+    BinIndex,
+    Curve(Vec<f64>),
+}
+
 /// ## Amplitude Scaled Delay
 ///
-/// Each bin is delayed by an amount relative to it's amplitude. Delay is measured in frames that are bins/time-div/sample-rate seconds long.
+/// Each bin is delayed by an amount relative to it's amplitude, or to another property of the bin given by the mapping. Delay is measured in frames that are bins/time-div/sample-rate seconds long.
+/// Each bin replaces what is in the buffer at its delay. Fractional delays split the bin over the two nearest frames, where it replaces its share of the frame and adds up with the other bins written there since the frame was last read.
 ///
 /// - Delay: amount of time to delay by [0.0, 2000.0]
+/// - Mapping: what the delay is relative to
+/// - Delay sync: delay following the tempo, replaces Delay when set
+/// - Time divs: the number of overlapping frames of the phase vocoder, used to convert beats to frames
 /// - Max delay: delay buffer size [1, 2000]
 /// - Frequency/amplitude mix: mixer for delayed/original signal [0.0, 1.0]
 /// - Frequency/amplitude feedback: multiplier for previously read events - at 1, samples will remain in the buffer until they are overwritten, possibly looping after the max delay. [0.0, 1.0]
 pub struct AmpDelay {
     buffer: Vec<Vec<Vec<Bin>>>,
+    fresh: Vec<Vec<Vec<Bin>>>,
+    weight: Vec<Vec<Vec<f64>>>,
     time: usize,
-    pub delay: f64,
+    pub delay: ChannelParam,
+    pub delay_sync: Option<TempoSync>,
+    pub time_div: usize,
+    pub mapping: DelayMapping,
     pub max_delay: usize,
-    pub freq_mix: f64,
-    pub amp_mix: f64,
-    pub freq_feedback: f64,
-    pub amp_feedback: f64,
+    pub freq_mix: ChannelParam,
+    pub amp_mix: ChannelParam,
+    pub freq_feedback: ChannelParam,
+    pub amp_feedback: ChannelParam,
 }
 
 impl AmpDelay {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         channels: usize,
         bins: usize,
+        time_div: usize,
         delay: f64,
         max_delay: usize,
         freq_mix: f64,
@@ -33,51 +62,156 @@ impl AmpDelay {
         amp_feedback: f64,
     ) -> Self {
         Self {
-            buffer: vec![vec![vec![Bin::new(0.0, 0.0); bins]; channels]; 0],
+            buffer: vec![vec![vec![Bin::new(0.0, 0.0); bins]; channels]; max_delay.clamp(1, 2000)],
+            fresh: vec![vec![vec![Bin::new(0.0, 0.0); bins]; channels]; max_delay.clamp(1, 2000)],
+            weight: vec![vec![vec![0.0; bins]; channels]; max_delay.clamp(1, 2000)],
             time: 0,
-            delay,
+            delay: delay.into(),
+            delay_sync: None,
+            time_div,
+            mapping: DelayMapping::Amplitude,
             max_delay,
-            freq_mix,
-            amp_mix,
-            freq_feedback,
-            amp_feedback,
+            freq_mix: freq_mix.into(),
+            amp_mix: amp_mix.into(),
+            freq_feedback: freq_feedback.into(),
+            amp_feedback: amp_feedback.into(),
         }
     }
 }
 
+impl AmpDelay {
+    /// Writes `share` of `bin` to frame `slot`, making room by scaling down the bins written there since it was last read.
+    fn write(&mut self, slot: usize, i: usize, j: usize, bin: Bin, share: f64) {
+        let weight = &mut self.weight[slot][i][j];
+        let fresh = &mut self.fresh[slot][i][j];
+        if *weight + share > 1.0 {
+            fresh.amp *= (1.0 - share) / *weight;
+            *weight = 1.0;
+        } else {
+            *weight += share;
+        }
+        let amp = bin.amp * share;
+        fresh.freq = if fresh.amp + amp > 0.0 {
+            (fresh.freq * fresh.amp + bin.freq * amp) / (fresh.amp + amp)
+        } else {
+            bin.freq
+        };
+        fresh.amp += amp;
+    }
+
+    /// Reads the current frame: what is left of the buffer after the writes replaced their share of it, plus the writes.
+    fn read(&mut self, i: usize, j: usize) -> Bin {
+        let weight = std::mem::take(&mut self.weight[self.time][i][j]);
+        let fresh = std::mem::replace(&mut self.fresh[self.time][i][j], Bin::new(0.0, 0.0));
+        let stale = self.buffer[self.time][i][j];
+        let stale_amp = stale.amp * (1.0 - weight);
+        let amp = stale_amp + fresh.amp;
+        let freq = if stale_amp > 0.0 {
+            (stale.freq * stale_amp + fresh.freq * fresh.amp) / amp
+        } else if weight > 0.0 {
+            fresh.freq
+        } else {
+            stale.freq
+        };
+        Bin::new(freq, amp)
+    }
+}
+
 impl PVocMiniPlugin for AmpDelay {
     fn process(
         &mut self,
-        _sample_rate: f64,
+        sample_rate: f64,
         channels: usize,
         bins: usize,
         input: &[Vec<Bin>],
         output: &mut [Vec<Bin>],
     ) {
-        let delay = self.delay.clamp(0.0, 2000.0);
         let max_delay = self.max_delay.clamp(1, 2000);
-        let freq_mix = self.freq_mix.clamp(0.0, 1.0);
-        let amp_mix = self.amp_mix.clamp(0.0, 1.0);
-        let freq_feedback = self.freq_feedback.clamp(0.0, 1.0);
-        let amp_feedback = self.amp_feedback.clamp(0.0, 1.0);
-        let buffer = &mut self.buffer;
 
-        if max_delay != self.max_delay {
-            self.max_delay = max_delay;
-            buffer.resize(max_delay, vec![vec![Bin::new(0.0, 0.0); bins]; channels]);
+        if self.buffer.len() != max_delay {
+            let empty = vec![vec![Bin::new(0.0, 0.0); bins]; channels];
+            self.buffer.resize(max_delay, empty.clone());
+            self.fresh.resize(max_delay, empty);
+            self.weight
+                .resize(max_delay, vec![vec![0.0; bins]; channels]);
         }
 
+        let nyquist = sample_rate / 2.0;
         self.time %= max_delay;
         for i in 0..channels {
+            let delay = match self.delay_sync {
+                Some(sync) => sync.frames(sample_rate, bins, self.time_div),
+                None => self.delay.get(i),
+            };
+            let delay = delay.clamp(0.0, 2000.0);
+            let freq_mix = self.freq_mix.get(i).clamp(0.0, 1.0);
+            let amp_mix = self.amp_mix.get(i).clamp(0.0, 1.0);
+            let freq_feedback = self.freq_feedback.get(i).clamp(0.0, 1.0);
+            let amp_feedback = self.amp_feedback.get(i).clamp(0.0, 1.0);
             for j in 0..bins {
-                let bin_delay = ((input[i][j].amp + 1.0).log2() * delay) as usize;
-                buffer[(self.time + bin_delay) % max_delay][i][j] = input[i][j];
-                output[i][j].amp = lerp(buffer[self.time][i][j].amp, input[i][j].amp, amp_mix);
-                output[i][j].freq = lerp(buffer[self.time][i][j].freq, input[i][j].freq, freq_mix);
-                buffer[self.time][i][j].freq *= freq_feedback;
-                buffer[self.time][i][j].amp *= amp_feedback;
+                let position = bin_freq(sample_rate, bins, j) / nyquist;
+                let scale = match &self.mapping {
+                    DelayMapping::Amplitude => (input[i][j].amp + 1.0).log2(),
+                    DelayMapping::Frequency => (input[i][j].freq.abs() / nyquist).clamp(0.0, 1.0),
+                    DelayMapping::BinIndex => position,
+                    DelayMapping::Curve(curve) => sample_curve(curve, position).clamp(0.0, 1.0),
+                };
+                let bin_delay = scale * delay;
+                let frac = bin_delay.fract();
+                let near = (self.time + bin_delay as usize) % max_delay;
+                let far = (near + 1) % max_delay;
+                for (slot, share) in [(near, 1.0 - frac), (far, frac)] {
+                    if share > 0.0 {
+                        self.write(slot, i, j, input[i][j], share);
+                    }
+                }
+
+                let read = self.read(i, j);
+                output[i][j].amp = lerp(read.amp, input[i][j].amp, amp_mix);
+                output[i][j].freq = lerp(read.freq, input[i][j].freq, freq_mix);
+                self.buffer[self.time][i][j] =
+                    Bin::new(read.freq * freq_feedback, read.amp * amp_feedback);
             }
         }
         self.time += 1;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(delay: &mut AmpDelay, bin: Bin, frames: usize) -> Vec<Bin> {
+        let input = vec![vec![bin; 4]];
+        let mut output = vec![vec![Bin::new(0.0, 0.0); 4]];
+        (0..frames)
+            .map(|_| {
+                delay.process(44100.0, 1, 4, &input, &mut output);
+                output[0][1]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn constant_input_with_full_feedback_stays_bounded() {
+        for delay in [0.25, 0.5, 1.5, 2.75, 7.1] {
+            let mut amp_delay = AmpDelay::new(1, 4, 4, delay, 16, 1.0, 1.0, 1.0, 1.0);
+            amp_delay.mapping = DelayMapping::Curve(vec![1.0]);
+            for bin in run(&mut amp_delay, Bin::new(440.0, 1.0), 1000) {
+                assert!(bin.amp <= 1.0 + 1e-9, "delay {delay}: amp {}", bin.amp);
+                assert!((bin.freq - 440.0).abs() < 1e-9 || bin.amp == 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn integer_delay_overwrites() {
+        let mut amp_delay = AmpDelay::new(1, 4, 4, 3.0, 16, 1.0, 1.0, 1.0, 1.0);
+        amp_delay.mapping = DelayMapping::Curve(vec![1.0]);
+        let out = run(&mut amp_delay, Bin::new(440.0, 0.5), 20);
+        assert!(out[..3].iter().all(|bin| bin.amp == 0.0));
+        assert!(out[3..]
+            .iter()
+            .all(|bin| bin.freq == 440.0 && bin.amp == 0.5));
+    }
+}
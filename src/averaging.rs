@@ -0,0 +1,76 @@
+use super::lerp;
+
+/// Direction of exponential averaging across frequency.
+///
+/// - Up: from the lowest bin towards nyquist
+/// - Down: from nyquist towards the lowest bin
+/// - ZeroPhase: up then down again, so the average is centred on each bin
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AverageDirection {
+    Up,
+    Down,
+    ZeroPhase,
+}
+
+/// Width of exponential averaging across frequency, used instead of a raw alpha.
+///
+/// - Hz: the same width in Hz for every bin
+/// - Octaves: a width relative to the frequency of each bin
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bandwidth {
+    Hz(f64),
+    Octaves(f64),
+}
+
+impl Bandwidth {
+    /// Averaging alpha equivalent to this bandwidth at `freq`.
+    pub fn alpha(&self, freq: f64, freq_per_bin: f64) -> f64 {
+        let width = match *self {
+            Bandwidth::Hz(hz) => hz.max(0.0),
+            Bandwidth::Octaves(octaves) => freq * (octaves.max(0.0).exp2() - 1.0),
+        };
+        if width <= 0.0 {
+            0.0
+        } else {
+            (-freq_per_bin / width).exp()
+        }
+    }
+}
+
+/// Exponential average of `values` across bins in `direction`, where `alpha(j)` is the weight of the average at bin `j`.
+/// Each bin gets the average of the bins before it, starting from the first one.
+pub(crate) fn average_across(
+    values: &[f64],
+    direction: AverageDirection,
+    alpha: impl Fn(usize) -> f64,
+    averages: &mut Vec<f64>,
+) {
+    let up = |values: &[f64], averages: &mut Vec<f64>| {
+        averages.clear();
+        let mut avg = values.first().copied().unwrap_or(0.0);
+        for (j, &value) in values.iter().enumerate() {
+            averages.push(avg);
+            avg = lerp(avg, value, alpha(j));
+        }
+    };
+    let down = |averages: &mut Vec<f64>, values: &[f64]| {
+        let mut avg = values.last().copied().unwrap_or(0.0);
+        for (j, &value) in values.iter().enumerate().rev() {
+            averages[j] = avg;
+            avg = lerp(avg, value, alpha(j));
+        }
+    };
+    match direction {
+        AverageDirection::Up => up(values, averages),
+        AverageDirection::Down => {
+            averages.clear();
+            averages.resize(values.len(), 0.0);
+            down(averages, values);
+        }
+        AverageDirection::ZeroPhase => {
+            up(values, averages);
+            let forward = averages.clone();
+            down(averages, &forward);
+        }
+    }
+}
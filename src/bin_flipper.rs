@@ -1,18 +1,39 @@
+use super::clear;
+use super::pitch::{accumulate, mirror_lower_half};
+use super::ChannelParam;
 use super::PVocMiniPlugin;
 use pvoc::Bin;
 
+/// What the [`BinFlipper`] mirrors frequencies around.
+///
+/// - PerBin: each bin is mirrored around its own center frequency, scaled by the nyquist multiplier
+/// - Spectrum: bins with a center frequency between low and high, in Hz, are moved to the bin mirrored around the axis, in Hz [0.0, nyquist]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FlipMode {
+    PerBin,
+    Spectrum { axis: f64, low: f64, high: f64 },
+}
+
 /// ## Bin Flipper
 ///
-/// This linearly inverts the frequency of each bin.
+/// This linearly inverts the frequency of each bin, or inverts the whole spectrum around an axis.
+/// Both modes take the center frequency of bin j as j times the bin width, where the phase vocoder puts it.
 ///
-/// - Nyquist multiplier: multiplier for the center frequencies of the bins. [0.0, 1.0]
+/// - Nyquist multiplier: multiplier for the center frequencies of the bins, only used per bin. [0.0, 1.0]
+/// - Mode: mirror each bin or the spectrum, bins outside of the spectrum range pass through
 pub struct BinFlipper {
-    pub nyquist_multiplier: f64,
+    strongest: Vec<f64>,
+    pub nyquist_multiplier: ChannelParam,
+    pub mode: FlipMode,
 }
 
 impl BinFlipper {
     pub fn new(nyquist_multiplier: f64) -> Self {
-        Self { nyquist_multiplier }
+        Self {
+            strongest: Vec::new(),
+            nyquist_multiplier: nyquist_multiplier.into(),
+            mode: FlipMode::PerBin,
+        }
     }
 }
 
@@ -25,15 +46,52 @@ impl PVocMiniPlugin for BinFlipper {
         input: &[Vec<Bin>],
         output: &mut [Vec<Bin>],
     ) {
-        let mult = self.nyquist_multiplier.clamp(0.0, 1.0);
-        let freq_per_bin = sample_rate / (bins as f64) * mult;
+        let FlipMode::Spectrum { axis, low, high } = self.mode else {
+            for i in 0..channels {
+                let mult = self.nyquist_multiplier.get(i).clamp(0.0, 1.0);
+                let freq_per_bin = sample_rate / (bins as f64) * mult;
+                for j in 0..bins {
+                    let expect = freq_per_bin * (j as f64);
+                    let new = -(input[i][j].freq - expect) + expect;
+                    output[i][j].amp = input[i][j].amp;
+                    output[i][j].freq = new;
+                }
+            }
+            return;
+        };
+
+        let nyquist = sample_rate / 2.0;
+        let axis = axis.clamp(0.0, nyquist);
+        let low = low.clamp(0.0, nyquist);
+        let high = high.clamp(low, nyquist);
+        let freq_per_bin = sample_rate / (bins as f64);
+
+        clear(output, channels, bins);
         for i in 0..channels {
-            for j in 0..bins {
-                let expect = freq_per_bin * (j as f64) + freq_per_bin / 2.0;
-                let new = -(input[i][j].freq - expect) + expect;
-                output[i][j].amp = input[i][j].amp;
-                output[i][j].freq = new;
+            self.strongest.clear();
+            self.strongest.resize(bins, 0.0);
+            for (j, bin) in input[i].iter().enumerate().take(bins / 2 + 1) {
+                let centre = freq_per_bin * (j as f64);
+                let (index, freq) = if centre < low || centre > high {
+                    (j as f64, bin.freq)
+                } else {
+                    (
+                        ((2.0 * axis - centre) / freq_per_bin).round(),
+                        2.0 * axis - bin.freq,
+                    )
+                };
+                if index < 0.0 || index as usize > bins / 2 {
+                    continue;
+                }
+                let index = index as usize;
+                accumulate(
+                    &mut output[i][index],
+                    &mut self.strongest[index],
+                    freq,
+                    bin.amp,
+                );
             }
+            mirror_lower_half(&mut output[i], sample_rate, bins);
         }
     }
 }
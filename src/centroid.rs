@@ -1,14 +1,36 @@
+use super::lerp;
+use super::pitch::{estimate_fundamental, nearest_harmonic};
+use super::ChannelParam;
 use super::PVocMiniPlugin;
 use pvoc::Bin;
 
+/// What the [`Centroid`] fixes the frequency of each bin to.
+///
+/// - BinCentre: the center frequency of the bin
+/// - Harmonic: the nearest harmonic of the fundamental in Hz, estimated per channel when not given. Frequencies are left untouched when no fundamental is found.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CentroidMode {
+    BinCentre,
+    Harmonic { fundamental: Option<f64> },
+}
+
 /// ## Centroid
 ///
-/// Fixes the frequency of each bin directly to the center.
-pub struct Centroid;
+/// Fixes the frequency of each bin directly to the center, or to the nearest harmonic.
+///
+/// - Amount: mixer for fixed/original frequency [0.0, 1.0]
+/// - Mode: what frequencies are fixed to
+pub struct Centroid {
+    pub amount: ChannelParam,
+    pub mode: CentroidMode,
+}
 
 impl Centroid {
     pub fn new() -> Self {
-        Centroid {}
+        Centroid {
+            amount: 1.0.into(),
+            mode: CentroidMode::BinCentre,
+        }
     }
 }
 
@@ -23,10 +45,24 @@ impl PVocMiniPlugin for Centroid {
     ) {
         let freq_per_bin = sample_rate / (bins as f64);
         for i in 0..channels {
+            let amount = self.amount.get(i).clamp(0.0, 1.0);
+            let fundamental = match self.mode {
+                CentroidMode::BinCentre => None,
+                CentroidMode::Harmonic {
+                    fundamental: Some(f0),
+                } => Some(f0.clamp(1.0, sample_rate / 2.0)),
+                CentroidMode::Harmonic { fundamental: None } => {
+                    estimate_fundamental(&input[i], bins, sample_rate)
+                }
+            };
             for j in 0..bins {
-                let expect = freq_per_bin * (j as f64) + freq_per_bin / 2.0;
+                let expect = match (self.mode, fundamental) {
+                    (CentroidMode::BinCentre, _) => freq_per_bin * (j as f64) + freq_per_bin / 2.0,
+                    (_, Some(f0)) => nearest_harmonic(input[i][j].freq, f0),
+                    (_, None) => input[i][j].freq,
+                };
                 output[i][j].amp = input[i][j].amp;
-                output[i][j].freq = expect;
+                output[i][j].freq = lerp(expect, input[i][j].freq, amount);
             }
         }
     }
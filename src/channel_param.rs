@@ -0,0 +1,42 @@
+/// A plugin parameter with a value for each channel, e.g. to detune the left and right channels of a [`PitchShifter`](crate::PitchShifter) differently.
+///
+/// - Linked: `value` on the first channel, every following channel adding `offset`
+/// - PerChannel: the value of each channel, channels past the end of the list take the last value
+///
+/// Parameters that place bins across the channels, like pans, stay a single value.
+/// Numbers convert to a linked parameter without offset and lists of numbers to per channel values, so `shifter.shift = 1.5.into()` sets every channel.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChannelParam {
+    Linked { value: f64, offset: f64 },
+    PerChannel(Vec<f64>),
+}
+
+impl ChannelParam {
+    pub fn linked(value: f64, offset: f64) -> Self {
+        ChannelParam::Linked { value, offset }
+    }
+
+    /// Value of the parameter on `channel`.
+    pub fn get(&self, channel: usize) -> f64 {
+        match self {
+            ChannelParam::Linked { value, offset } => value + offset * channel as f64,
+            ChannelParam::PerChannel(values) => values
+                .get(channel)
+                .or(values.last())
+                .copied()
+                .unwrap_or(0.0),
+        }
+    }
+}
+
+impl From<f64> for ChannelParam {
+    fn from(value: f64) -> Self {
+        ChannelParam::linked(value, 0.0)
+    }
+}
+
+impl From<Vec<f64>> for ChannelParam {
+    fn from(values: Vec<f64>) -> Self {
+        ChannelParam::PerChannel(values)
+    }
+}
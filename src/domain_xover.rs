@@ -1,23 +1,54 @@
-use super::lerp;
+use super::averaging::average_across;
+use super::bin_freq;
+use super::AverageDirection;
+use super::Bandwidth;
+use super::ChannelParam;
 use super::PVocMiniPlugin;
 use pvoc::Bin;
 
+/// Which amplitudes modulate the frequencies of the channels of the [`DomainXOver`].
+///
+/// - Own: each channel is modulated by its own amplitude
+/// - Channel: every channel is modulated by the amplitude of one channel, e.g. the left channel driving the right one, or an extra sidechain channel driving all the others, such as mid driving side in a [`MidSide`](crate::MidSide) with the sidechain on
+/// - Mid: every channel is modulated by the mean amplitude of all channels
+/// - Side: every channel is modulated by half the spread between the loudest and the quietest channel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModulationSource {
+    Own,
+    Channel(usize),
+    Mid,
+    Side,
+}
+
 /// ## Domain Crossover
 ///
 /// Modulates frequency of the bins based on their amplitude.
 ///
 /// - Add: Ring modulation factor - intensity of frequency modulation. [0.0, 2.5]
-/// Shift: Frequency offset. [0.0, 1.0]
-/// Alpha: Exponential averaging alpha for amplitude estimate. [0.0, 1.0]
+/// - Shift: Frequency offset. [0.0, 1.0]
+/// - Alpha: Exponential averaging alpha for amplitude estimate. [0.0, 1.0]
+/// - Direction: direction of the amplitude averaging across frequency
+/// - Bandwidth: width of the amplitude averaging in Hz or octaves, replaces Alpha when set
+/// - Source: the amplitudes driving the modulation
 pub struct DomainXOver {
-    pub add: f64,
-    pub shift: f64,
-    pub alpha: f64,
+    pub add: ChannelParam,
+    pub shift: ChannelParam,
+    pub alpha: ChannelParam,
+    pub direction: AverageDirection,
+    pub bandwidth: Option<Bandwidth>,
+    pub source: ModulationSource,
 }
 
 impl DomainXOver {
     pub fn new(add: f64, shift: f64, alpha: f64) -> Self {
-        Self { add, shift, alpha }
+        Self {
+            add: add.into(),
+            shift: shift.into(),
+            alpha: alpha.into(),
+            direction: AverageDirection::Up,
+            bandwidth: None,
+            source: ModulationSource::Own,
+        }
     }
 }
 
@@ -31,16 +62,44 @@ impl PVocMiniPlugin for DomainXOver {
         output: &mut [Vec<Bin>],
     ) {
         let freq_per_bin = sample_rate / (bins as f64);
-        let add = self.add.clamp(0.0, 25.0);
-        let shift = self.shift.clamp(0.0, 1.0);
-        let alpha = self.alpha.clamp(0.0, 1.0);
+        let bandwidth = self.bandwidth;
+
+        let mut amps = Vec::with_capacity(bins);
+        let mut avg = Vec::with_capacity(bins);
         for i in 0..channels {
-            let mut avg = input[i][0].amp;
+            let add = self.add.get(i).clamp(0.0, 25.0);
+            let shift = self.shift.get(i).clamp(0.0, 1.0);
+            let alpha = self.alpha.get(i).clamp(0.0, 1.0);
+            let alpha = |j: usize| match bandwidth {
+                Some(bandwidth) => bandwidth.alpha(bin_freq(sample_rate, bins, j), freq_per_bin),
+                None => alpha,
+            };
+            amps.clear();
+            match self.source {
+                ModulationSource::Own => amps.extend(input[i][..bins].iter().map(|bin| bin.amp)),
+                ModulationSource::Channel(source) => {
+                    let source = source.min(channels - 1);
+                    amps.extend(input[source][..bins].iter().map(|bin| bin.amp))
+                }
+                ModulationSource::Mid => amps.extend((0..bins).map(|j| {
+                    input[..channels]
+                        .iter()
+                        .map(|channel| channel[j].amp)
+                        .sum::<f64>()
+                        / (channels as f64)
+                })),
+                ModulationSource::Side => amps.extend((0..bins).map(|j| {
+                    let channel_amps = input[..channels].iter().map(|channel| channel[j].amp);
+                    let max = channel_amps.clone().fold(f64::MIN, f64::max);
+                    let min = channel_amps.fold(f64::MAX, f64::min);
+                    (max - min) / 2.0
+                })),
+            }
+            average_across(&amps, self.direction, alpha, &mut avg);
             for j in 0..bins {
                 output[i][j].freq =
-                    input[i][j].freq + shift * freq_per_bin + ((avg - input[i][j].amp) * add);
+                    input[i][j].freq + shift * freq_per_bin + ((avg[j] - amps[j]) * add);
                 output[i][j].amp = input[i][j].amp;
-                avg = lerp(avg, input[i][j].amp, alpha);
             }
         }
     }
@@ -1,29 +1,42 @@
+use super::averaging::average_across;
+use super::bin_freq;
 use super::lerp;
+use super::AverageDirection;
+use super::Bandwidth;
+use super::ChannelParam;
 use super::PVocMiniPlugin;
 use pvoc::Bin;
 
 /// ## Exponential Averaging
 ///
-/// Modulates frequency and amplitude of bins based on exponential average of lower pitched bins.
+/// Modulates frequency and amplitude of bins based on exponential average of lower pitched bins, or of higher pitched or surrounding bins depending on the direction.
 ///
 /// - Frequency alpha: exponential averaging alpha for frequency [0.0, 1.0]
 /// - Amplitude alpha: exponential averaging alpha for amplitude [0.0, 1.0]
 /// - Frequency mix: Mixer for original/modulated frequency [0.0, 1.0]
 /// - Amplitude mix: Mixer for original/modulated amplitude [0.0, 1.0]
+/// - Direction: direction of the averaging across frequency
+/// - Frequency/amplitude bandwidth: width of the averaging in Hz or octaves, replaces the alpha when set
 pub struct ExpAvg {
-    pub freq_alpha: f64,
-    pub amp_alpha: f64,
-    pub freq_mix: f64,
-    pub amp_mix: f64,
+    pub freq_alpha: ChannelParam,
+    pub amp_alpha: ChannelParam,
+    pub freq_mix: ChannelParam,
+    pub amp_mix: ChannelParam,
+    pub direction: AverageDirection,
+    pub freq_bandwidth: Option<Bandwidth>,
+    pub amp_bandwidth: Option<Bandwidth>,
 }
 
 impl ExpAvg {
     pub fn new(freq_alpha: f64, amp_alpha: f64, freq_mix: f64, amp_mix: f64) -> Self {
         Self {
-            freq_alpha,
-            amp_alpha,
-            freq_mix,
-            amp_mix,
+            freq_alpha: freq_alpha.into(),
+            amp_alpha: amp_alpha.into(),
+            freq_mix: freq_mix.into(),
+            amp_mix: amp_mix.into(),
+            direction: AverageDirection::Up,
+            freq_bandwidth: None,
+            amp_bandwidth: None,
         }
     }
 }
@@ -31,24 +44,47 @@ impl ExpAvg {
 impl PVocMiniPlugin for ExpAvg {
     fn process(
         &mut self,
-        _sample_rate: f64,
+        sample_rate: f64,
         channels: usize,
         bins: usize,
         input: &[Vec<Bin>],
         output: &mut [Vec<Bin>],
     ) {
-        let freq_alpha = self.freq_alpha.clamp(0.0, 1.0);
-        let amp_alpha = self.amp_alpha.clamp(0.0, 1.0);
-        let freq_mix = self.freq_mix.clamp(0.0, 1.0);
-        let amp_mix = self.amp_mix.clamp(0.0, 1.0);
+        let freq_per_bin = sample_rate / (bins as f64);
+        let alpha = |bandwidth: Option<Bandwidth>, raw: f64| {
+            move |j: usize| match bandwidth {
+                Some(bandwidth) => bandwidth.alpha(bin_freq(sample_rate, bins, j), freq_per_bin),
+                None => raw,
+            }
+        };
+
+        let mut values = Vec::with_capacity(bins);
+        let mut avg_freq = Vec::with_capacity(bins);
+        let mut avg_amp = Vec::with_capacity(bins);
         for i in 0..channels {
-            let mut avg_freq = input[i][0].freq;
-            let mut avg_amp = input[i][0].amp;
+            let freq_alpha = self.freq_alpha.get(i).clamp(0.0, 1.0);
+            let amp_alpha = self.amp_alpha.get(i).clamp(0.0, 1.0);
+            let freq_mix = self.freq_mix.get(i).clamp(0.0, 1.0);
+            let amp_mix = self.amp_mix.get(i).clamp(0.0, 1.0);
+            values.clear();
+            values.extend(input[i][..bins].iter().map(|bin| bin.freq));
+            average_across(
+                &values,
+                self.direction,
+                alpha(self.freq_bandwidth, freq_alpha),
+                &mut avg_freq,
+            );
+            values.clear();
+            values.extend(input[i][..bins].iter().map(|bin| bin.amp));
+            average_across(
+                &values,
+                self.direction,
+                alpha(self.amp_bandwidth, amp_alpha),
+                &mut avg_amp,
+            );
             for j in 0..bins {
-                output[i][j].freq = lerp(avg_freq, input[i][j].freq, freq_mix);
-                output[i][j].amp = lerp(avg_amp, input[i][j].amp, amp_mix);
-                avg_freq = lerp(avg_freq, input[i][j].freq, freq_alpha);
-                avg_amp = lerp(avg_amp, input[i][j].amp, amp_alpha);
+                output[i][j].freq = lerp(avg_freq[j], input[i][j].freq, freq_mix);
+                output[i][j].amp = lerp(avg_amp[j], input[i][j].amp, amp_mix);
             }
         }
     }
@@ -1,3 +1,4 @@
+use super::ChannelParam;
 use super::PVocMiniPlugin;
 use pvoc::Bin;
 
@@ -5,12 +6,14 @@ use pvoc::Bin;
 ///
 /// - Shift: Shift factor [0.0, 8.0]
 pub struct FreqShifter {
-    pub shift: f64,
+    pub shift: ChannelParam,
 }
 
 impl FreqShifter {
     pub fn new(shift: f64) -> Self {
-        Self { shift }
+        Self {
+            shift: shift.into(),
+        }
     }
 }
 
@@ -23,9 +26,8 @@ impl PVocMiniPlugin for FreqShifter {
         input: &[Vec<Bin>],
         output: &mut [Vec<Bin>],
     ) {
-        let shift = self.shift.clamp(0.0, 8.0);
-
         for i in 0..channels {
+            let shift = self.shift.get(i).clamp(0.0, 8.0);
             for j in 0..bins / 2 {
                 let index = ((j as f64) * shift) as usize;
                 if index < bins / 2 {
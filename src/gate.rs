@@ -1,3 +1,10 @@
+use super::amp_to_dbfs;
+use super::bin_freq;
+use super::frame_seconds;
+use super::lerp;
+use super::smoothstep;
+use super::time_constant;
+use super::ChannelParam;
 use super::PVocMiniPlugin;
 use pvoc::Bin;
 
@@ -5,40 +12,136 @@ use pvoc::Bin;
 ///
 /// Filter out loud/quiet sounds
 ///
-/// - Gate: don't let sounds through that are quieter than this threshold [0.0, 8.0]
-/// - Duck: don't let sounds through that are louder than this threshold [0.0, 8.0]
+/// - Gate: don't let sounds through that are quieter than this threshold in dBFS [-120.0, 0.0]
+/// - Duck: don't let sounds through that are louder than this threshold in dBFS [-120.0, 0.0]
+/// - Knee: width of the soft transition around the thresholds in dB [0.0, 48.0]
+/// - Hysteresis: how far past the thresholds an open bin has to go before it closes again in dB [0.0, 24.0]
+/// - Tilt: offset added to the gate threshold per octave above 1 kHz in dB [-24.0, 24.0]
+/// - Curve: breakpoints of (frequency in Hz, offset in dB) in any order, added to the gate threshold and interpolated over octaves, or linearly from a breakpoint at 0 Hz
+/// - Attack: time for a bin to open in milliseconds [0.0, 1000.0]
+/// - Hold: time a bin stays open after falling out of the thresholds in milliseconds [0.0, 1000.0]
+/// - Release: time for a bin to close in milliseconds [0.0, 5000.0]
+/// - Floor: gain applied to closed bins instead of silencing them [0.0, 1.0]
+/// - Time divs: the number of overlapping frames of the phase vocoder, used to convert times to frames
 pub struct Gate {
-    pub gate: f64,
-    pub duck: f64,
+    gain: Vec<Vec<f64>>,
+    hold_left: Vec<Vec<usize>>,
+    open: Vec<Vec<bool>>,
+    offset: Vec<f64>,
+    sorted_curve: Vec<(f64, f64)>,
+    pub gate: ChannelParam,
+    pub duck: ChannelParam,
+    pub knee: ChannelParam,
+    pub hysteresis: ChannelParam,
+    pub tilt: ChannelParam,
+    pub curve: Vec<(f64, f64)>,
+    pub attack: ChannelParam,
+    pub hold: ChannelParam,
+    pub release: ChannelParam,
+    pub floor: ChannelParam,
+    pub time_div: usize,
 }
 
 impl Gate {
-    pub fn new(gate: f64, duck: f64) -> Self {
-        Self { gate, duck }
+    const TILT_PIVOT: f64 = 1000.0;
+
+    pub fn new(channels: usize, bins: usize, time_div: usize, gate: f64, duck: f64) -> Self {
+        Self {
+            gain: vec![vec![0.0; bins]; channels],
+            hold_left: vec![vec![0; bins]; channels],
+            open: vec![vec![false; bins]; channels],
+            offset: vec![0.0; bins],
+            sorted_curve: Vec::new(),
+            gate: gate.into(),
+            duck: duck.into(),
+            knee: 0.0.into(),
+            hysteresis: 0.0.into(),
+            tilt: 0.0.into(),
+            curve: Vec::new(),
+            attack: 0.0.into(),
+            hold: 0.0.into(),
+            release: 0.0.into(),
+            floor: 0.0.into(),
+            time_div,
+        }
+    }
+
+    /// Threshold offset in dB at `freq` given by the tilt and the breakpoints of `curve` sorted by frequency.
+    fn threshold_offset(tilt: f64, curve: &[(f64, f64)], freq: f64) -> f64 {
+        let tilt = tilt * (freq / Self::TILT_PIVOT).log2();
+        let curve = match curve.iter().position(|&(f, _)| f >= freq) {
+            None => curve.last().map_or(0.0, |&(_, db)| db),
+            Some(0) => curve[0].1,
+            Some(k) => {
+                let (f0, db0) = curve[k - 1];
+                let (f1, db1) = curve[k];
+                let x = if f1 <= f0 {
+                    1.0
+                } else if f0 <= 0.0 {
+                    (freq - f0) / (f1 - f0)
+                } else {
+                    (freq / f0).log2() / (f1 / f0).log2()
+                };
+                lerp(db1, db0, x)
+            }
+        };
+        tilt + curve
     }
 }
 
 impl PVocMiniPlugin for Gate {
     fn process(
         &mut self,
-        _sample_rate: f64,
+        sample_rate: f64,
         channels: usize,
         bins: usize,
         input: &[Vec<Bin>],
         output: &mut [Vec<Bin>],
     ) {
-        let gate = self.gate.clamp(0.0, 8.0);
-        let duck = self.duck.clamp(0.0, 8.0);
+        let hop = frame_seconds(sample_rate, bins, self.time_div);
+        self.sorted_curve.clone_from(&self.curve);
+        self.sorted_curve.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self.offset.resize(bins, 0.0);
+
         for i in 0..channels {
+            let gate = self.gate.get(i).clamp(-120.0, 0.0);
+            let duck = self.duck.get(i).clamp(-120.0, 0.0);
+            let knee = self.knee.get(i).clamp(0.0, 48.0) / 2.0;
+            let hysteresis = self.hysteresis.get(i).clamp(0.0, 24.0);
+            let floor = self.floor.get(i).clamp(0.0, 1.0);
+            let attack = time_constant(self.attack.get(i).clamp(0.0, 1000.0), hop);
+            let release = time_constant(self.release.get(i).clamp(0.0, 5000.0), hop);
+            let hold = (self.hold.get(i).clamp(0.0, 1000.0) / 1000.0 / hop).round() as usize;
+
+            let tilt = self.tilt.get(i).clamp(-24.0, 24.0);
+            for j in 0..bins {
+                let freq = bin_freq(sample_rate, bins, j.max(1));
+                self.offset[j] = Self::threshold_offset(tilt, &self.sorted_curve, freq);
+            }
+
             for j in 0..bins {
                 output[i][j].freq = input[i][j].freq;
-                let amp = (input[i][j].amp + 1.0).log2();
-                // TODO smooth it out a bit at the boundary
-                output[i][j].amp = if amp < gate || amp > duck {
-                    0.0
+                let amp = amp_to_dbfs(input[i][j].amp, bins);
+                let gate = gate + self.offset[j];
+                let (gate, duck) = if self.open[i][j] {
+                    (gate - hysteresis, duck + hysteresis)
+                } else {
+                    (gate, duck)
+                };
+                let target = smoothstep(gate - knee, gate + knee, amp)
+                    * (1.0 - smoothstep(duck - knee, duck + knee, amp));
+                self.open[i][j] = target >= 0.5;
+
+                let gain = &mut self.gain[i][j];
+                if target >= *gain {
+                    *gain = lerp(*gain, target, attack);
+                    self.hold_left[i][j] = hold;
+                } else if self.hold_left[i][j] > 0 {
+                    self.hold_left[i][j] -= 1;
                 } else {
-                    input[i][j].amp
+                    *gain = lerp(*gain, target, release);
                 }
+                output[i][j].amp = input[i][j].amp * lerp(1.0, floor, *gain);
             }
         }
     }
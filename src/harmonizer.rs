@@ -0,0 +1,97 @@
+use super::clear;
+use super::pitch::shift_bins;
+use super::ChannelParam;
+use super::PVocMiniPlugin;
+use super::Scale;
+use pvoc::Bin;
+use std::f64::consts::FRAC_PI_4;
+
+/// A single pitch-shifted voice of the [`Harmonizer`].
+///
+/// - Interval: shift in semitones [-48.0, 48.0]
+/// - Gain: amplitude multiplier [0.0, 2.0]
+/// - Pan: stereo position with constant power, only used with two channels [-1.0, 1.0]
+#[derive(Clone, Debug)]
+pub struct Voice {
+    pub interval: ChannelParam,
+    pub gain: ChannelParam,
+    pub pan: f64,
+}
+
+impl Voice {
+    pub fn new(interval: f64, gain: f64, pan: f64) -> Self {
+        Self {
+            interval: interval.into(),
+            gain: gain.into(),
+            pan,
+        }
+    }
+}
+
+/// ## Harmonizer
+///
+/// Sums several pitch-shifted copies of the input spectrum.
+/// If a scale is set, the interval of each voice is snapped to the nearest degree of the scale.
+///
+/// - Voices: the voices to generate, a voice with interval 0.0 passes the input through
+/// - Scale: optional scale to quantize intervals to
+pub struct Harmonizer {
+    strongest: Vec<Vec<f64>>,
+    pub voices: Vec<Voice>,
+    pub scale: Option<Scale>,
+}
+
+impl Harmonizer {
+    pub fn new(voices: Vec<Voice>) -> Self {
+        Self {
+            strongest: Vec::new(),
+            voices,
+            scale: None,
+        }
+    }
+}
+
+impl PVocMiniPlugin for Harmonizer {
+    fn process(
+        &mut self,
+        sample_rate: f64,
+        channels: usize,
+        bins: usize,
+        input: &[Vec<Bin>],
+        output: &mut [Vec<Bin>],
+    ) {
+        clear(output, channels, bins);
+        self.strongest.resize(channels, Vec::new());
+        for strongest in self.strongest.iter_mut() {
+            strongest.clear();
+            strongest.resize(bins, 0.0);
+        }
+
+        for voice in &self.voices {
+            let angle = (voice.pan.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+
+            for i in 0..channels {
+                let mut interval = voice.interval.get(i).clamp(-48.0, 48.0);
+                if let Some(scale) = &self.scale {
+                    interval = scale.quantize(interval);
+                }
+                let ratio = (interval / 12.0).exp2();
+                let gain = voice.gain.get(i).clamp(0.0, 2.0);
+                let pan = match (channels, i) {
+                    (2, 0) => angle.cos(),
+                    (2, _) => angle.sin(),
+                    _ => 1.0,
+                };
+                shift_bins(
+                    &input[i],
+                    &mut output[i],
+                    &mut self.strongest[i],
+                    sample_rate,
+                    bins,
+                    ratio,
+                    gain * pan,
+                );
+            }
+        }
+    }
+}
@@ -4,40 +4,70 @@
 //! GPL-3.0
 
 use pvoc::Bin;
+use std::f64::consts::PI;
 
 mod amp_delay;
+mod averaging;
 mod bin_flipper;
 mod centroid;
+mod channel_param;
 mod domain_xover;
 mod exp_avg;
 mod formant_shifter;
 mod freq_shifter;
 mod gate;
+mod harmonizer;
+mod loop_band;
+mod mid_side;
 mod modular_amp;
+mod pitch;
+mod pitch_corrector;
 mod pitch_shifter;
 mod repeater;
+mod robotize;
+mod scale;
 mod scrambler;
 mod slope_filter;
+mod spectral_delay;
+mod spectral_eq;
+mod spectral_panner;
 mod stencil;
+mod tempo_sync;
 mod through;
 mod time_blur;
+mod transient_splitter;
+mod whisperize;
 
-pub use amp_delay::AmpDelay;
-pub use bin_flipper::BinFlipper;
-pub use centroid::Centroid;
-pub use domain_xover::DomainXOver;
+pub use amp_delay::{AmpDelay, DelayMapping};
+pub use averaging::{AverageDirection, Bandwidth};
+pub use bin_flipper::{BinFlipper, FlipMode};
+pub use centroid::{Centroid, CentroidMode};
+pub use channel_param::ChannelParam;
+pub use domain_xover::{DomainXOver, ModulationSource};
 pub use exp_avg::ExpAvg;
 pub use formant_shifter::FormantShifter;
 pub use freq_shifter::FreqShifter;
 pub use gate::Gate;
-pub use modular_amp::ModularAmp;
+pub use harmonizer::{Harmonizer, Voice};
+pub use loop_band::LoopBand;
+pub use mid_side::MidSide;
+pub use modular_amp::{AmpDomain, ModularAmp, WrapShape};
+pub use pitch_corrector::PitchCorrector;
 pub use pitch_shifter::PitchShifter;
-pub use repeater::Repeater;
-pub use scrambler::Scrambler;
+pub use repeater::{Repeater, RepeaterState};
+pub use robotize::Robotize;
+pub use scale::Scale;
+pub use scrambler::{Scrambler, ScramblerMode};
 pub use slope_filter::SlopeFilter;
-pub use stencil::Stencil;
+pub use spectral_delay::{BinCurve, SpectralDelay};
+pub use spectral_eq::{EqBand, SpectralEq};
+pub use spectral_panner::{PanLaw, PanMode, SpectralPanner};
+pub use stencil::{Kernel, Stencil, StencilNormalization};
+pub use tempo_sync::TempoSync;
 pub use through::Through;
-pub use time_blur::TimeBlur;
+pub use time_blur::{BlurTime, TimeBlur};
+pub use transient_splitter::TransientSplitter;
+pub use whisperize::Whisperize;
 
 /// The trait that is implemented to each plugins.
 /// - Bins log2: the number of frequency bins used for the phase vocoder. Few will likely be low quality and many will blur the audio through time. Somewhere between 6 and 13 is usually what you want.
@@ -64,3 +94,87 @@ fn fmod(a: f64, b: f64) -> f64 {
         a - b * (a / b).floor()
     }
 }
+
+/// Frequency in Hz of bin `j`, where bins above nyquist mirror the ones below it.
+fn bin_freq(sample_rate: f64, bins: usize, j: usize) -> f64 {
+    sample_rate / (bins as f64) * (mirrored_bin(bins, j) as f64)
+}
+
+/// Index of the bin below nyquist that bin `j` mirrors, `j` itself for the bins up to nyquist.
+fn mirrored_bin(bins: usize, j: usize) -> usize {
+    j.min(bins - j)
+}
+
+/// Linearly interpolates `curve` at `x` [0.0, 1.0], where the values of `curve` are spread evenly from 0.0 to 1.0.
+fn sample_curve(curve: &[f64], x: f64) -> f64 {
+    match curve.len() {
+        0 => 0.0,
+        1 => curve[0],
+        len => {
+            let position = x.clamp(0.0, 1.0) * ((len - 1) as f64);
+            let index = (position as usize).min(len - 2);
+            lerp(curve[index + 1], curve[index], position - index as f64)
+        }
+    }
+}
+
+/// Smooth transition from 0.0 below `edge0` to 1.0 above `edge1`, or a hard step if they are equal.
+fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    if edge1 <= edge0 {
+        return if x < edge0 { 0.0 } else { 1.0 };
+    }
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Converts a bin amplitude to dBFS, where 0.0 dB is a full scale sine centred on the bin.
+/// The phase vocoder uses a square root Hann window, which sums to 2 * bins / pi.
+fn amp_to_dbfs(amp: f64, bins: usize) -> f64 {
+    20.0 * (amp * PI / (2.0 * bins as f64)).log10()
+}
+
+/// Converts dBFS back to a bin amplitude, see `amp_to_dbfs`.
+fn dbfs_to_amp(dbfs: f64, bins: usize) -> f64 {
+    10f64.powf(dbfs / 20.0) * 2.0 * (bins as f64) / PI
+}
+
+/// Duration of a frame in seconds, i.e. the hop between overlapping frames of the phase vocoder.
+fn frame_seconds(sample_rate: f64, bins: usize, time_div: usize) -> f64 {
+    (bins as f64) / (time_div.max(1) as f64) / sample_rate
+}
+
+/// Per-frame coefficient of an exponential smoother with a time constant of `ms` milliseconds, to be used as `x` of `lerp`.
+/// `hop` is the duration of a frame in seconds.
+fn time_constant(ms: f64, hop: f64) -> f64 {
+    if ms <= 0.0 {
+        0.0
+    } else {
+        (-hop / (ms / 1000.0)).exp()
+    }
+}
+
+/// Xorshift generator so that randomized plugins are reproducible from a seed.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    /// Returns a value in [0.0, 1.0).
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Zeroes the bins of `output` for plugins that accumulate into it.
+fn clear(output: &mut [Vec<Bin>], channels: usize, bins: usize) {
+    for channel in output.iter_mut().take(channels) {
+        for bin in channel.iter_mut().take(bins) {
+            *bin = Bin::new(0.0, 0.0);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use super::bin_freq;
+
+/// A frequency band with its own loop, used by the [`Scrambler`](crate::Scrambler) and [`Repeater`](crate::Repeater).
+///
+/// A band covers the frequencies above the previous band up to its upper edge, bins above the last band belong to the last band.
+///
+/// - Upper: upper edge of the band in Hz
+/// - Length: loop length of the band in frames
+/// - Increment: read index increment in frames, only used by the Scrambler
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoopBand {
+    pub upper: f64,
+    pub length: usize,
+    pub increment: usize,
+}
+
+impl LoopBand {
+    pub fn new(upper: f64, length: usize, increment: usize) -> Self {
+        Self {
+            upper,
+            length,
+            increment,
+        }
+    }
+
+    /// Splits the spectrum up to `nyquist` in Hz into `count` bands of equal width [1, 64], all with the same loop.
+    pub fn equal_width(count: usize, nyquist: f64, length: usize, increment: usize) -> Vec<Self> {
+        let count = count.clamp(1, 64);
+        (1..=count)
+            .map(|k| Self::new(nyquist * (k as f64) / (count as f64), length, increment))
+            .collect()
+    }
+}
+
+/// Index of the band of `bands` bin `j` belongs to, or 0 if there are no bands.
+pub(crate) fn band_of(bands: &[LoopBand], sample_rate: f64, bins: usize, j: usize) -> usize {
+    let freq = bin_freq(sample_rate, bins, j);
+    bands
+        .iter()
+        .position(|band| freq <= band.upper)
+        .unwrap_or(bands.len().saturating_sub(1))
+}
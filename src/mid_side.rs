@@ -0,0 +1,131 @@
+use super::clear;
+use super::PVocMiniPlugin;
+use pvoc::Bin;
+
+/// ## Mid/Side
+///
+/// Converts a stereo pair of spectra to mid and side, runs one plugin on each and converts back.
+/// Both plugins process a single channel, or two with the sidechain on, so plugins with buffers have to be created for as many channels.
+/// With any other number of channels than two, the input is passed through unchanged.
+///
+/// Since bins carry no phase, the conversion works per bin on amplitudes: mid is the mean of both channels and side is half their difference.
+/// Mid takes the amplitude weighted mean frequency of both channels and side the frequency of the louder one.
+/// Each channel keeps its own frequency, moved by the frequency changes the plugins made.
+///
+/// With the sidechain on, each plugin gets a second channel holding the other spectrum, mid for the side plugin and side for the mid plugin, and only its first channel is used.
+/// This lets one drive the other, e.g. a [`DomainXOver`](crate::DomainXOver) with the source set to channel 1 as the side plugin modulates side by the amplitude of mid.
+///
+/// - Mid: plugin processing the mid spectrum
+/// - Side: plugin processing the side spectrum
+/// - Sidechain: hand each plugin the other spectrum as a second channel
+pub struct MidSide<M: PVocMiniPlugin, S: PVocMiniPlugin> {
+    mid_in: Vec<Vec<Bin>>,
+    side_in: Vec<Vec<Bin>>,
+    mid_out: Vec<Vec<Bin>>,
+    side_out: Vec<Vec<Bin>>,
+    left_louder: Vec<bool>,
+    freqs: Vec<[f64; 2]>,
+    pub mid: M,
+    pub side: S,
+    pub sidechain: bool,
+}
+
+impl<M: PVocMiniPlugin, S: PVocMiniPlugin> MidSide<M, S> {
+    pub fn new(bins: usize, mid: M, side: S) -> Self {
+        Self {
+            mid_in: vec![vec![Bin::new(0.0, 0.0); bins]; 2],
+            side_in: vec![vec![Bin::new(0.0, 0.0); bins]; 2],
+            mid_out: vec![vec![Bin::new(0.0, 0.0); bins]; 2],
+            side_out: vec![vec![Bin::new(0.0, 0.0); bins]; 2],
+            left_louder: vec![true; bins],
+            freqs: vec![[0.0; 2]; bins],
+            mid,
+            side,
+            sidechain: false,
+        }
+    }
+}
+
+impl<M: PVocMiniPlugin, S: PVocMiniPlugin> PVocMiniPlugin for MidSide<M, S> {
+    fn process(
+        &mut self,
+        sample_rate: f64,
+        channels: usize,
+        bins: usize,
+        input: &[Vec<Bin>],
+        output: &mut [Vec<Bin>],
+    ) {
+        if channels != 2 {
+            for (input, output) in input.iter().zip(output.iter_mut()).take(channels) {
+                output[..bins].copy_from_slice(&input[..bins]);
+            }
+            return;
+        }
+
+        for (j, (left, right)) in input[0].iter().zip(&input[1]).enumerate().take(bins) {
+            let sum = left.amp + right.amp;
+            let mid_freq = if sum > 0.0 {
+                (left.freq * left.amp + right.freq * right.amp) / sum
+            } else {
+                (left.freq + right.freq) / 2.0
+            };
+            self.left_louder[j] = left.amp >= right.amp;
+            self.freqs[j] = [left.freq, right.freq];
+            let side_freq = if self.left_louder[j] {
+                left.freq
+            } else {
+                right.freq
+            };
+            self.mid_in[0][j] = Bin::new(mid_freq, sum / 2.0);
+            self.side_in[0][j] = Bin::new(side_freq, (left.amp - right.amp).abs() / 2.0);
+        }
+
+        let plugin_channels = if self.sidechain {
+            self.mid_in[1][..bins].copy_from_slice(&self.side_in[0][..bins]);
+            self.side_in[1][..bins].copy_from_slice(&self.mid_in[0][..bins]);
+            2
+        } else {
+            1
+        };
+        clear(&mut self.mid_out, plugin_channels, bins);
+        clear(&mut self.side_out, plugin_channels, bins);
+        self.mid.process(
+            sample_rate,
+            plugin_channels,
+            bins,
+            &self.mid_in,
+            &mut self.mid_out,
+        );
+        self.side.process(
+            sample_rate,
+            plugin_channels,
+            bins,
+            &self.side_in,
+            &mut self.side_out,
+        );
+
+        let (left, right) = output.split_at_mut(1);
+        let pairs = left[0].iter_mut().zip(right[0].iter_mut());
+        for (j, (left, right)) in pairs.enumerate().take(bins) {
+            let (mid, side) = (self.mid_out[0][j], self.side_out[0][j]);
+            let mid_shift = mid.freq - self.mid_in[0][j].freq;
+            let side_shift = side.freq - self.side_in[0][j].freq;
+            let [left_freq, right_freq] = self.freqs[j];
+            let (louder, louder_freq, quieter, quieter_freq) = if self.left_louder[j] {
+                (left, left_freq, right, right_freq)
+            } else {
+                (right, right_freq, left, left_freq)
+            };
+            let sum = mid.amp + side.amp;
+            louder.amp = sum.max(0.0);
+            louder.freq = louder_freq
+                + if sum > 0.0 {
+                    (mid_shift * mid.amp + side_shift * side.amp) / sum
+                } else {
+                    mid_shift
+                };
+            quieter.amp = (mid.amp - side.amp).max(0.0);
+            quieter.freq = quieter_freq + mid_shift;
+        }
+    }
+}
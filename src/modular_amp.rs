@@ -1,36 +1,110 @@
+use super::amp_to_dbfs;
+use super::dbfs_to_amp;
 use super::fmod;
+use super::mirrored_bin;
+use super::ChannelParam;
 use super::PVocMiniPlugin;
 use pvoc::Bin;
 
+/// Scale the modulus of the [`ModularAmp`] is applied on.
+///
+/// - Linear: linear amplitude, Mod is a divisor [0.0, 25.0]
+/// - Decibel: amplitude in dBFS, Mod is a range in dB below full scale [0.0, 96.0]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AmpDomain {
+    Linear,
+    Decibel,
+}
+
+/// Shape of the modulus of the [`ModularAmp`].
+///
+/// - Saw: wrap around to 0.0 when reaching the divisor
+/// - Fold: reflect back down when reaching the divisor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WrapShape {
+    Saw,
+    Fold,
+}
+
+impl WrapShape {
+    fn apply(&self, a: f64, b: f64) -> f64 {
+        if b <= 0.0 {
+            return a;
+        }
+        match self {
+            WrapShape::Saw => fmod(a, b),
+            WrapShape::Fold => b - (fmod(a, 2.0 * b) - b).abs(),
+        }
+    }
+}
+
 /// Modular Amplitude
 ///
-/// Performs floating point modulus on the amplitude of each bin.
+/// Performs floating point modulus on the amplitude of each bin, and optionally on its frequency, as a spectral wavefolder.
+/// Frequencies above the lower edge of the band are wrapped or folded into the band, frequencies below it are left untouched.
 ///
-/// - Mod: Divisor [0.0, 25.0]
+/// - Mod: Divisor [0.0, 25.0], or range in dB [0.0, 96.0] in the decibel domain
+/// - Domain: linear amplitude or dBFS
+/// - Shape: wrap around or fold back
+/// - Frequency mod: width of the frequency band in Hz, 0.0 leaves frequencies untouched [0.0, nyquist]
+/// - Frequency low: lower edge of the frequency band in Hz [0.0, nyquist]
 pub struct ModularAmp {
-    pub factor: f64,
+    pub factor: ChannelParam,
+    pub domain: AmpDomain,
+    pub shape: WrapShape,
+    pub freq_factor: ChannelParam,
+    pub freq_low: ChannelParam,
 }
 
 impl ModularAmp {
     pub fn new(factor: f64) -> Self {
-        Self { factor }
+        Self {
+            factor: factor.into(),
+            domain: AmpDomain::Linear,
+            shape: WrapShape::Saw,
+            freq_factor: 0.0.into(),
+            freq_low: 0.0.into(),
+        }
     }
 }
 
 impl PVocMiniPlugin for ModularAmp {
     fn process(
         &mut self,
-        _sample_rate: f64,
+        sample_rate: f64,
         channels: usize,
         bins: usize,
         input: &[Vec<Bin>],
         output: &mut [Vec<Bin>],
     ) {
-        let factor = self.factor.clamp(0.0, 25.0);
+        let nyquist = sample_rate / 2.0;
         for i in 0..channels {
+            let factor = self.factor.get(i);
+            let freq_factor = self.freq_factor.get(i).clamp(0.0, nyquist);
+            let freq_low = self.freq_low.get(i).clamp(0.0, nyquist);
+            let wrap_freq = |freq: f64| {
+                if freq_factor <= 0.0 || freq < freq_low {
+                    freq
+                } else {
+                    freq_low + self.shape.apply(freq - freq_low, freq_factor)
+                }
+            };
             for j in 0..bins {
-                output[i][j].freq = input[i][j].freq;
-                output[i][j].amp = fmod(input[i][j].amp, factor);
+                // bins above nyquist carry the mirrored frequency of the bin they mirror
+                output[i][j].freq = if mirrored_bin(bins, j) == j {
+                    wrap_freq(input[i][j].freq)
+                } else {
+                    sample_rate - wrap_freq(sample_rate - input[i][j].freq)
+                };
+                output[i][j].amp = match self.domain {
+                    AmpDomain::Linear => self.shape.apply(input[i][j].amp, factor.clamp(0.0, 25.0)),
+                    AmpDomain::Decibel if input[i][j].amp > 0.0 => {
+                        let range = factor.clamp(0.0, 96.0);
+                        let db = amp_to_dbfs(input[i][j].amp, bins);
+                        dbfs_to_amp(self.shape.apply(db, range) - range, bins)
+                    }
+                    AmpDomain::Decibel => 0.0,
+                };
             }
         }
     }
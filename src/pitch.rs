@@ -0,0 +1,78 @@
+use pvoc::Bin;
+
+/// Adds a contribution of `amp` at `freq` to `bin`, which takes the frequency of its strongest contributor.
+/// `strongest` is the amplitude of the strongest contribution so far, 0.0 for a cleared bin.
+pub(crate) fn accumulate(bin: &mut Bin, strongest: &mut f64, freq: f64, amp: f64) {
+    if amp >= *strongest {
+        *strongest = amp;
+        bin.freq = freq;
+    }
+    bin.amp += amp;
+}
+
+/// Writes the bins up to nyquist of `output` to the bins above it, which hold the same partials at negative frequencies.
+pub(crate) fn mirror_lower_half(output: &mut [Bin], sample_rate: f64, bins: usize) {
+    for j in 1..bins.div_ceil(2) {
+        output[bins - j] = Bin::new(sample_rate - output[j].freq, output[j].amp);
+    }
+}
+
+/// Moves the bins of `input` up to nyquist to the bins scaled by `ratio` and accumulates them onto `output` with `gain`, then mirrors them above nyquist.
+/// Each destination bin takes the frequency of its strongest contributor, tracked in `strongest` across calls.
+pub(crate) fn shift_bins(
+    input: &[Bin],
+    output: &mut [Bin],
+    strongest: &mut [f64],
+    sample_rate: f64,
+    bins: usize,
+    ratio: f64,
+    gain: f64,
+) {
+    for (j, bin) in input.iter().enumerate().take(bins / 2 + 1) {
+        let index = ((j as f64) * ratio).round() as usize;
+        if index <= bins / 2 {
+            accumulate(
+                &mut output[index],
+                &mut strongest[index],
+                bin.freq * ratio,
+                bin.amp * gain,
+            );
+        }
+    }
+    mirror_lower_half(output, sample_rate, bins);
+}
+
+/// Frequency of the harmonic of `fundamental` nearest to `freq`, at least the fundamental itself.
+pub(crate) fn nearest_harmonic(freq: f64, fundamental: f64) -> f64 {
+    (freq / fundamental).round().max(1.0) * fundamental
+}
+
+/// Estimates the fundamental frequency of a channel from the instantaneous frequencies of its bins.
+/// The loudest bin and its subharmonics are the candidates, scored by the mean amplitude at their first harmonics.
+pub(crate) fn estimate_fundamental(input: &[Bin], bins: usize, sample_rate: f64) -> Option<f64> {
+    const HARMONICS: usize = 8;
+    let freq_per_bin = sample_rate / (bins as f64);
+    let half = &input[..bins / 2];
+    let peak = half[1..].iter().max_by(|a, b| a.amp.total_cmp(&b.amp))?;
+    if peak.amp <= 0.0 || peak.freq < freq_per_bin {
+        return None;
+    }
+
+    let mut best: Option<(f64, f64)> = None;
+    for k in 1..=4 {
+        let f0 = peak.freq / (k as f64);
+        if f0 < freq_per_bin {
+            break;
+        }
+        let amps: Vec<f64> = (1..=HARMONICS)
+            .map(|h| ((h as f64) * f0 / freq_per_bin).round() as usize)
+            .take_while(|&index| index < half.len())
+            .map(|index| half[index].amp)
+            .collect();
+        let score = amps.iter().sum::<f64>() / (amps.len().max(1) as f64);
+        if best.is_none_or(|(_, s)| score > s) {
+            best = Some((f0, score));
+        }
+    }
+    best.map(|(f0, _)| f0)
+}
@@ -0,0 +1,70 @@
+use super::clear;
+use super::lerp;
+use super::pitch::estimate_fundamental;
+use super::pitch::shift_bins;
+use super::ChannelParam;
+use super::PVocMiniPlugin;
+use super::Scale;
+use pvoc::Bin;
+
+/// ## Pitch Corrector
+///
+/// Estimates the fundamental of each channel every frame, snaps it to the nearest note of the scale and shifts the spectrum by the required ratio.
+///
+/// - Key: root note of the scale as a pitch class, 0 is C [0, 11]
+/// - Scale: notes the fundamental is snapped to
+/// - Speed: retune speed, at 1.0 the correction is applied instantly [0.0, 1.0]
+pub struct PitchCorrector {
+    ratio: Vec<f64>,
+    strongest: Vec<f64>,
+    pub key: usize,
+    pub scale: Scale,
+    pub speed: ChannelParam,
+}
+
+impl PitchCorrector {
+    pub fn new(channels: usize, key: usize, scale: Scale, speed: f64) -> Self {
+        Self {
+            ratio: vec![1.0; channels],
+            strongest: Vec::new(),
+            key,
+            scale,
+            speed: speed.into(),
+        }
+    }
+}
+
+impl PVocMiniPlugin for PitchCorrector {
+    fn process(
+        &mut self,
+        sample_rate: f64,
+        channels: usize,
+        bins: usize,
+        input: &[Vec<Bin>],
+        output: &mut [Vec<Bin>],
+    ) {
+        let key = self.key.clamp(0, 11) as f64;
+
+        clear(output, channels, bins);
+        for i in 0..channels {
+            let speed = self.speed.get(i).clamp(0.0, 1.0);
+            if let Some(f0) = estimate_fundamental(&input[i], bins, sample_rate) {
+                let note = 69.0 + 12.0 * (f0 / 440.0).log2();
+                let target = self.scale.quantize(note - key) + key;
+                let ratio = ((target - note) / 12.0).exp2();
+                self.ratio[i] = lerp(ratio, self.ratio[i], speed);
+            }
+            self.strongest.clear();
+            self.strongest.resize(bins, 0.0);
+            shift_bins(
+                &input[i],
+                &mut output[i],
+                &mut self.strongest,
+                sample_rate,
+                bins,
+                self.ratio[i],
+                1.0,
+            );
+        }
+    }
+}
@@ -1,31 +1,65 @@
 use super::lerp;
+use super::loop_band::band_of;
+use super::ChannelParam;
+use super::LoopBand;
 use super::PVocMiniPlugin;
+use super::TempoSync;
 use pvoc::Bin;
 
+/// Transport state of the [`Repeater`].
+///
+/// - Free: continuously blend the input into the loop buffer
+/// - Recording: capture the input, the loop length is the time spent recording
+/// - Overdubbing: play the loop while adding the input to it
+/// - Playing: play the loop
+/// - Stopped: pass the input through
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RepeaterState {
+    Free,
+    Recording,
+    Overdubbing,
+    Playing,
+    Stopped,
+}
+
 /// Repeater
 ///
 /// Capture a sound then repeat it indefinitely
 ///
-/// - Length: The length of the section to repeat, in frames [1, 2000]
+/// - Length: The length of the section to repeat, in frames, used when no bands are set [1, 2000]
 /// - Hold: Mixer for input signal/signal from loop buffer [0.0, 1.0]
 /// - Decay: Multiplier for buffer amplitude [0.0, 1.0]
 /// - Mix: Amplitude dry/wet. [0.0, 1.0]
+/// - Length sync: length following the tempo, replaces Length when set
+/// - Time divs: the number of overlapping frames of the phase vocoder, used to convert beats to frames
+/// - Bands: bands repeating sections of independent lengths [1, 2000], their increments are unused
+///
+/// Length, Hold, Length sync and Bands only apply in the free state. The transport methods switch to a looper that records a phrase on demand, where Decay only applies while overdubbing.
+/// Ending an empty recording stops the looper, and `free` returns to the free state.
 pub struct Repeater {
     buffer: Vec<Vec<Vec<Bin>>>,
-    time: usize,
+    time: Vec<usize>,
+    state: RepeaterState,
+    position: usize,
+    loop_length: usize,
     pub length: usize,
-    pub freq_hold: f64,
-    pub amp_hold: f64,
-    pub decay: f64,
-    pub mix: f64,
+    pub freq_hold: ChannelParam,
+    pub amp_hold: ChannelParam,
+    pub decay: ChannelParam,
+    pub mix: ChannelParam,
+    pub bands: Vec<LoopBand>,
+    pub length_sync: Option<TempoSync>,
+    pub time_div: usize,
 }
 
 impl Repeater {
     const MAX_LENGTH: usize = 2000;
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         channels: usize,
         bins: usize,
+        time_div: usize,
         length: usize,
         freq_hold: f64,
         amp_hold: f64,
@@ -34,12 +68,126 @@ impl Repeater {
     ) -> Self {
         Self {
             buffer: vec![vec![vec![Bin::new(0.0, 0.0); bins]; channels]; Self::MAX_LENGTH],
-            time: 0,
+            time: vec![0],
+            state: RepeaterState::Free,
+            position: 0,
+            loop_length: 0,
             length,
-            freq_hold,
-            amp_hold,
-            decay,
-            mix,
+            freq_hold: freq_hold.into(),
+            amp_hold: amp_hold.into(),
+            decay: decay.into(),
+            mix: mix.into(),
+            bands: Vec::new(),
+            length_sync: None,
+            time_div,
+        }
+    }
+
+    pub fn state(&self) -> RepeaterState {
+        self.state
+    }
+
+    /// Starts recording a new loop.
+    pub fn record(&mut self) {
+        self.state = RepeaterState::Recording;
+        self.position = 0;
+        self.loop_length = 0;
+    }
+
+    /// Adds the input to the loop, ending the recording if there is one.
+    pub fn overdub(&mut self) {
+        self.end_recording();
+        if self.loop_length > 0 {
+            self.state = RepeaterState::Overdubbing;
+        }
+    }
+
+    /// Plays the loop, ending the recording if there is one.
+    pub fn play(&mut self) {
+        self.end_recording();
+        if self.loop_length > 0 {
+            self.state = RepeaterState::Playing;
+        }
+    }
+
+    /// Stops the loop and passes the input through.
+    pub fn stop(&mut self) {
+        self.end_recording();
+        self.state = RepeaterState::Stopped;
+    }
+
+    /// Returns to continuously blending the input into the buffer, ending the recording if there is one.
+    pub fn free(&mut self) {
+        self.end_recording();
+        self.state = RepeaterState::Free;
+    }
+
+    /// Erases the loop and passes the input through.
+    pub fn clear(&mut self) {
+        for frame in self.buffer.iter_mut() {
+            for channel in frame.iter_mut() {
+                for bin in channel.iter_mut() {
+                    *bin = Bin::new(0.0, 0.0);
+                }
+            }
+        }
+        self.loop_length = 0;
+        self.position = 0;
+        self.state = RepeaterState::Stopped;
+    }
+
+    fn end_recording(&mut self) {
+        if self.state == RepeaterState::Recording {
+            self.loop_length = self.position;
+            self.position = 0;
+            self.state = RepeaterState::Stopped;
+        }
+    }
+
+    fn transport(
+        &mut self,
+        channels: usize,
+        bins: usize,
+        input: &[Vec<Bin>],
+        output: &mut [Vec<Bin>],
+    ) {
+        let frame = &mut self.buffer[self.position];
+        for i in 0..channels {
+            let decay = self.decay.get(i).clamp(0.0, 1.0);
+            let mix = self.mix.get(i).clamp(0.0, 1.0);
+            for j in 0..bins {
+                let looped = &mut frame[i][j];
+                match self.state {
+                    RepeaterState::Recording => {
+                        *looped = input[i][j];
+                        output[i][j] = input[i][j];
+                    }
+                    RepeaterState::Overdubbing | RepeaterState::Playing => {
+                        output[i][j].amp = lerp(looped.amp, input[i][j].amp, mix);
+                        output[i][j].freq = looped.freq;
+                        if self.state == RepeaterState::Overdubbing {
+                            if input[i][j].amp > looped.amp * decay {
+                                looped.freq = input[i][j].freq;
+                            }
+                            looped.amp = looped.amp * decay + input[i][j].amp;
+                        }
+                    }
+                    RepeaterState::Free | RepeaterState::Stopped => output[i][j] = input[i][j],
+                }
+            }
+        }
+
+        match self.state {
+            RepeaterState::Recording => {
+                self.position += 1;
+                if self.position == Self::MAX_LENGTH {
+                    self.play();
+                }
+            }
+            RepeaterState::Overdubbing | RepeaterState::Playing => {
+                self.position = (self.position + 1) % self.loop_length;
+            }
+            RepeaterState::Free | RepeaterState::Stopped => {}
         }
     }
 }
@@ -47,33 +195,52 @@ impl Repeater {
 impl PVocMiniPlugin for Repeater {
     fn process(
         &mut self,
-        _sample_rate: f64,
+        sample_rate: f64,
         channels: usize,
         bins: usize,
         input: &[Vec<Bin>],
         output: &mut [Vec<Bin>],
     ) {
-        let length = self.length.clamp(1, Self::MAX_LENGTH);
-        let freq_hold = self.freq_hold.clamp(0.0, 1.0);
-        let amp_hold = self.amp_hold.clamp(0.0, 1.0);
-        let decay = self.decay.clamp(0.0, 1.0);
-        let mix = self.mix.clamp(0.0, 1.0);
+        if self.state != RepeaterState::Free {
+            self.transport(channels, bins, input, output);
+            return;
+        }
+
+        let length = match self.length_sync {
+            Some(sync) => sync.frames(sample_rate, bins, self.time_div).round() as usize,
+            None => self.length,
+        };
+        let lengths: Vec<usize> = if self.bands.is_empty() {
+            vec![length.clamp(1, Self::MAX_LENGTH)]
+        } else {
+            self.bands
+                .iter()
+                .map(|band| band.length.clamp(1, Self::MAX_LENGTH))
+                .collect()
+        };
 
-        self.time %= length;
+        self.time.resize(lengths.len(), 0);
+        for (time, &length) in self.time.iter_mut().zip(&lengths) {
+            *time %= length;
+        }
         for i in 0..channels {
+            let freq_hold = self.freq_hold.get(i).clamp(0.0, 1.0);
+            let amp_hold = self.amp_hold.get(i).clamp(0.0, 1.0);
+            let decay = self.decay.get(i).clamp(0.0, 1.0);
+            let mix = self.mix.get(i).clamp(0.0, 1.0);
             for j in 0..bins {
-                self.buffer[self.time][i][j].amp =
-                    lerp(self.buffer[self.time][i][j].amp, input[i][j].amp, amp_hold);
-                self.buffer[self.time][i][j].freq = lerp(
-                    self.buffer[self.time][i][j].freq,
-                    input[i][j].freq,
-                    freq_hold,
-                );
-                output[i][j].amp = lerp(self.buffer[self.time][i][j].amp, input[i][j].amp, mix);
-                output[i][j].freq = self.buffer[self.time][i][j].freq;
-                self.buffer[self.time][i][j].amp *= decay;
+                let time = self.time[band_of(&self.bands, sample_rate, bins, j)];
+                self.buffer[time][i][j].amp =
+                    lerp(self.buffer[time][i][j].amp, input[i][j].amp, amp_hold);
+                self.buffer[time][i][j].freq =
+                    lerp(self.buffer[time][i][j].freq, input[i][j].freq, freq_hold);
+                output[i][j].amp = lerp(self.buffer[time][i][j].amp, input[i][j].amp, mix);
+                output[i][j].freq = self.buffer[time][i][j].freq;
+                self.buffer[time][i][j].amp *= decay;
             }
         }
-        self.time += 1;
+        for time in self.time.iter_mut() {
+            *time += 1;
+        }
     }
 }
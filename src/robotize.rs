@@ -0,0 +1,46 @@
+use super::lerp;
+use super::pitch::nearest_harmonic;
+use super::ChannelParam;
+use super::PVocMiniPlugin;
+use pvoc::Bin;
+
+/// ## Robotize
+///
+/// Pins the frequency of each bin to the nearest harmonic of a fixed fundamental.
+///
+/// - Fundamental: frequency of the robot voice in Hz [20.0, 2000.0]
+/// - Amount: Mixer for harmonic/original frequency [0.0, 1.0]
+pub struct Robotize {
+    pub fundamental: ChannelParam,
+    pub amount: ChannelParam,
+}
+
+impl Robotize {
+    pub fn new(fundamental: f64, amount: f64) -> Self {
+        Self {
+            fundamental: fundamental.into(),
+            amount: amount.into(),
+        }
+    }
+}
+
+impl PVocMiniPlugin for Robotize {
+    fn process(
+        &mut self,
+        _sample_rate: f64,
+        channels: usize,
+        bins: usize,
+        input: &[Vec<Bin>],
+        output: &mut [Vec<Bin>],
+    ) {
+        for i in 0..channels {
+            let fundamental = self.fundamental.get(i).clamp(20.0, 2000.0);
+            let amount = self.amount.get(i).clamp(0.0, 1.0);
+            for j in 0..bins {
+                let harmonic = nearest_harmonic(input[i][j].freq, fundamental);
+                output[i][j].amp = input[i][j].amp;
+                output[i][j].freq = lerp(harmonic, input[i][j].freq, amount);
+            }
+        }
+    }
+}
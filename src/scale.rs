@@ -0,0 +1,41 @@
+/// Musical scale used to quantize intervals and pitches.
+///
+/// Degrees are semitone offsets from the root of the scale, in [0, 12).
+#[derive(Clone, Debug, PartialEq)]
+pub enum Scale {
+    Chromatic,
+    Major,
+    Minor,
+    HarmonicMinor,
+    PentatonicMajor,
+    PentatonicMinor,
+    Custom(Vec<usize>),
+}
+
+impl Scale {
+    pub fn degrees(&self) -> &[usize] {
+        match self {
+            Scale::Chromatic => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            Scale::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Scale::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            Scale::HarmonicMinor => &[0, 2, 3, 5, 7, 8, 11],
+            Scale::PentatonicMajor => &[0, 2, 4, 7, 9],
+            Scale::PentatonicMinor => &[0, 3, 5, 7, 10],
+            Scale::Custom(degrees) => degrees,
+        }
+    }
+
+    /// Snaps a number of semitones above the root to the nearest degree of the scale.
+    pub fn quantize(&self, semitones: f64) -> f64 {
+        let octave = (semitones / 12.0).floor();
+        let within = semitones - octave * 12.0;
+        let nearest = self
+            .degrees()
+            .iter()
+            .map(|&degree| (degree % 12) as f64)
+            .chain(std::iter::once(12.0))
+            .min_by(|a, b| (a - within).abs().total_cmp(&(b - within).abs()))
+            .unwrap_or(within);
+        octave * 12.0 + nearest
+    }
+}
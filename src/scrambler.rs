@@ -1,30 +1,89 @@
+use super::loop_band::band_of;
+use super::LoopBand;
 use super::PVocMiniPlugin;
+use super::Rng;
+use super::TempoSync;
 use pvoc::Bin;
 
+/// How the [`Scrambler`] moves its read position through the circular buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScramblerMode {
+    Increment,
+    Reverse,
+    PingPong,
+    Random,
+}
+
 /// Scrambler
 ///
 /// Reorder frames by indexing into a circular buffer by some specified increment.
+/// The spectrum can be split into bands with their own length and increment which read different frames: in random mode every band draws its own frame, in the other modes the bands start spread across their buffers.
 ///
-/// - Length: Circular buffer length [1, 4096]
-/// - Increment: Circular buffer index increment [1, 4096]
+/// - Length: Circular buffer length, used when no bands are set [1, 4096]
+/// - Increment: Circular buffer index increment, used when no bands are set [1, 4096]
+/// - Length sync: length following the tempo, replaces Length when set
+/// - Time divs: the number of overlapping frames of the phase vocoder, used to convert beats to frames
+/// - Mode: step forward, step backward, bounce between the ends of the buffer or pick random frames
+/// - Bands: bands with independent lengths [1, 4096] and increments [1, 4096], `LoopBand::equal_width` splits the spectrum evenly
+/// - Seed: seed of the random generator
 pub struct Scrambler {
     pub length: usize,
     pub increment: usize,
+    pub length_sync: Option<TempoSync>,
+    pub time_div: usize,
+    pub mode: ScramblerMode,
+    pub bands: Vec<LoopBand>,
+    pub seed: u64,
     buffer: Vec<Vec<Vec<Bin>>>,
-    time: usize,
-    k: usize,
+    time: Vec<usize>,
+    k: Vec<usize>,
+    rng: Rng,
+    rng_seed: u64,
 }
 
 impl Scrambler {
     const MAX_LENGTH: usize = 4096;
 
-    pub fn new(channels: usize, bins: usize, length: usize, increment: usize) -> Self {
+    pub fn new(
+        channels: usize,
+        bins: usize,
+        time_div: usize,
+        length: usize,
+        increment: usize,
+    ) -> Self {
         Self {
             buffer: vec![vec![vec![Bin::new(0.0, 0.0); bins]; channels]; Self::MAX_LENGTH],
-            time: 0,
-            k: 0,
+            time: vec![0],
+            k: vec![0],
+            rng: Rng::new(0),
+            rng_seed: 0,
             length,
             increment,
+            length_sync: None,
+            time_div,
+            mode: ScramblerMode::Increment,
+            bands: Vec::new(),
+            seed: 0,
+        }
+    }
+
+    fn ping_pong_period(length: usize) -> usize {
+        (2 * (length - 1)).max(1)
+    }
+
+    /// Frame of the buffer band `band` reads from.
+    fn read_index(&self, band: usize, length: usize) -> usize {
+        if self.mode == ScramblerMode::PingPong {
+            // `k` runs over the buffer and back again
+            let period = Self::ping_pong_period(length);
+            let position = self.k[band] % period;
+            if position < length {
+                position
+            } else {
+                period - position
+            }
+        } else {
+            self.k[band] % length
         }
     }
 }
@@ -32,26 +91,67 @@ impl Scrambler {
 impl PVocMiniPlugin for Scrambler {
     fn process(
         &mut self,
-        _sample_rate: f64,
+        sample_rate: f64,
         channels: usize,
         bins: usize,
         input: &[Vec<Bin>],
         output: &mut [Vec<Bin>],
     ) {
-        let length = self.length.clamp(1, Self::MAX_LENGTH);
-        let increment = self.increment.clamp(1, Self::MAX_LENGTH);
+        let clamp = |length: usize, increment: usize| {
+            (
+                length.clamp(1, Self::MAX_LENGTH),
+                increment.clamp(1, Self::MAX_LENGTH),
+            )
+        };
+        let length = match self.length_sync {
+            Some(sync) => sync.frames(sample_rate, bins, self.time_div).round() as usize,
+            None => self.length,
+        };
+        let loops: Vec<(usize, usize)> = if self.bands.is_empty() {
+            vec![clamp(length, self.increment)]
+        } else {
+            self.bands
+                .iter()
+                .map(|band| clamp(band.length, band.increment))
+                .collect()
+        };
+
+        if self.seed != self.rng_seed {
+            self.rng = Rng::new(self.seed);
+            self.rng_seed = self.seed;
+        }
+        if self.k.len() != loops.len() {
+            let count = loops.len();
+            self.time = vec![0; count];
+            self.k = (0..count)
+                .map(|band| band * loops[band].0 / count)
+                .collect();
+        }
 
-        self.time %= length;
-        self.k %= length;
+        for (time, &(length, _)) in self.time.iter_mut().zip(&loops) {
+            *time %= length;
+        }
         for i in 0..channels {
             for j in 0..bins {
-                self.buffer[self.time][i][j].amp = input[i][j].amp;
-                self.buffer[self.time][i][j].freq = input[i][j].freq;
-                output[i][j].amp = self.buffer[self.k][i][j].amp;
-                output[i][j].freq = self.buffer[self.k][i][j].freq;
+                let band = band_of(&self.bands, sample_rate, bins, j);
+                let time = self.time[band];
+                let k = self.read_index(band, loops[band].0);
+                self.buffer[time][i][j].amp = input[i][j].amp;
+                self.buffer[time][i][j].freq = input[i][j].freq;
+                output[i][j].amp = self.buffer[k][i][j].amp;
+                output[i][j].freq = self.buffer[k][i][j].freq;
             }
         }
-        self.time += 1;
-        self.k += increment;
+
+        for (band, &(length, increment)) in loops.iter().enumerate() {
+            self.time[band] += 1;
+            let k = self.k[band];
+            self.k[band] = match self.mode {
+                ScramblerMode::Increment => (k + increment) % length,
+                ScramblerMode::Reverse => (k + length - increment % length) % length,
+                ScramblerMode::PingPong => (k + increment) % Self::ping_pong_period(length),
+                ScramblerMode::Random => (self.rng.next_f64() * length as f64) as usize,
+            };
+        }
     }
 }
@@ -1,20 +1,35 @@
+use super::amp_to_dbfs;
+use super::smoothstep;
+use super::ChannelParam;
 use super::PVocMiniPlugin;
 use pvoc::Bin;
 
 /// ## Slope Filter
 /// Filter out sounds that are changing in frequency or amplitude
 ///
-/// - Freq min/max: thresholds for filter to activate [0.0, 0.1]
-/// - Amp min/max: thresholds for filter to activate [0.0, 8.0]
+/// Slopes are measured between the current frame and the frame a window earlier, averaged per frame.
+///
+/// - Freq min/max: thresholds for filter to activate, in cents per frame [0.0, 1200.0]
+/// - Amp min/max: thresholds for filter to activate, in dB per frame [0.0, 96.0]
+/// - Window: number of frames the slopes are measured over [1, 100]
+/// - Freq/amp knee: width of the gain rolloff outside of the thresholds, 0.0 cuts hard [0.0, 1200.0] and [0.0, 96.0]
+/// - Invert: keep only the sounds outside of the thresholds
 pub struct SlopeFilter {
-    buffer: Vec<Vec<Bin>>,
-    pub freq_min: f64,
-    pub freq_max: f64,
-    pub amp_min: f64,
-    pub amp_max: f64,
+    buffer: Vec<Vec<Vec<Bin>>>,
+    time: usize,
+    pub freq_min: ChannelParam,
+    pub freq_max: ChannelParam,
+    pub amp_min: ChannelParam,
+    pub amp_max: ChannelParam,
+    pub window: usize,
+    pub freq_knee: ChannelParam,
+    pub amp_knee: ChannelParam,
+    pub invert: bool,
 }
 
 impl SlopeFilter {
+    const MAX_WINDOW: usize = 100;
+
     pub fn new(
         channels: usize,
         bins: usize,
@@ -24,15 +39,25 @@ impl SlopeFilter {
         amp_max: f64,
     ) -> Self {
         Self {
-            buffer: vec![vec![Bin::new(0.0, 0.0); bins]; channels],
-            freq_min,
-            freq_max,
-            amp_min,
-            amp_max,
+            buffer: vec![vec![vec![Bin::new(0.0, 0.0); bins]; channels]; Self::MAX_WINDOW + 1],
+            time: 0,
+            freq_min: freq_min.into(),
+            freq_max: freq_max.into(),
+            amp_min: amp_min.into(),
+            amp_max: amp_max.into(),
+            window: 1,
+            freq_knee: 0.0.into(),
+            amp_knee: 0.0.into(),
+            invert: false,
         }
     }
 }
 
+/// Gain of a slope between `min` and `max`, rolling off over `knee` outside of them.
+fn band_gain(slope: f64, min: f64, max: f64, knee: f64) -> f64 {
+    smoothstep(min - knee, min, slope) * (1.0 - smoothstep(max, max + knee, slope))
+}
+
 impl PVocMiniPlugin for SlopeFilter {
     fn process(
         &mut self,
@@ -42,29 +67,33 @@ impl PVocMiniPlugin for SlopeFilter {
         input: &[Vec<Bin>],
         output: &mut [Vec<Bin>],
     ) {
-        let freq_min = self.freq_min.clamp(0.0, 0.1);
-        let freq_max = self.freq_max.clamp(0.0, 0.1);
-        let amp_min = self.amp_min.clamp(0.0, 8.0);
-        let amp_max = self.amp_max.clamp(0.0, 8.0);
+        let window = self.window.clamp(1, Self::MAX_WINDOW);
+        let length = Self::MAX_WINDOW + 1;
+        let oldest = (self.time + length - window) % length;
+        let db = |amp: f64| amp_to_dbfs(amp, bins).max(-120.0);
         for i in 0..channels {
+            let freq_min = self.freq_min.get(i).clamp(0.0, 1200.0);
+            let freq_max = self.freq_max.get(i).clamp(0.0, 1200.0);
+            let amp_min = self.amp_min.get(i).clamp(0.0, 96.0);
+            let amp_max = self.amp_max.get(i).clamp(0.0, 96.0);
+            let freq_knee = self.freq_knee.get(i).clamp(0.0, 1200.0);
+            let amp_knee = self.amp_knee.get(i).clamp(0.0, 96.0);
             for j in 0..bins {
                 output[i][j].freq = input[i][j].freq;
 
-                let amp_slope =
-                    ((input[i][j].amp + 1.0).log2() - (self.buffer[i][j].amp + 1.0).log2()).abs();
-                let freq_slope =
-                    ((input[i][j].freq + 1.0).log2() - (self.buffer[i][j].freq + 1.0).log2()).abs();
-                output[i][j].amp = if amp_slope < amp_min
-                    || amp_slope > amp_max
-                    || freq_slope < freq_min
-                    || freq_slope > freq_max
-                {
-                    0.0
-                } else {
-                    input[i][j].amp
-                };
-                self.buffer[i][j] = input[i][j];
+                let old = self.buffer[oldest][i][j];
+                let amp_slope = (db(input[i][j].amp) - db(old.amp)).abs() / (window as f64);
+                let freq_slope = 1200.0
+                    * (input[i][j].freq.abs().max(1.0) / old.freq.abs().max(1.0))
+                        .log2()
+                        .abs()
+                    / (window as f64);
+                let gain = band_gain(amp_slope, amp_min, amp_max, amp_knee)
+                    * band_gain(freq_slope, freq_min, freq_max, freq_knee);
+                output[i][j].amp = input[i][j].amp * if self.invert { 1.0 - gain } else { gain };
+                self.buffer[self.time][i][j] = input[i][j];
             }
         }
+        self.time = (self.time + 1) % length;
     }
 }
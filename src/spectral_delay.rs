@@ -0,0 +1,169 @@
+use super::clear;
+use super::lerp;
+use super::mirrored_bin;
+use super::sample_curve;
+use super::ChannelParam;
+use super::PVocMiniPlugin;
+use super::Rng;
+use pvoc::Bin;
+
+/// Shape of a per-bin parameter of the [`SpectralDelay`] from the lowest bin to nyquist, as a multiplier [0.0, 1.0].
+///
+/// - Flat: 1.0 for every bin
+/// - Rising: from 0.0 at the lowest bin to 1.0 at nyquist
+/// - Falling: from 1.0 at the lowest bin to 0.0 at nyquist
+/// - Random: a random value per bin, the same seed always gives the same values
+/// - Custom: values spread evenly between the lowest bin and nyquist, interpolated in between
+#[derive(Clone, Debug, PartialEq)]
+pub enum BinCurve {
+    Flat,
+    Rising,
+    Falling,
+    Random(u64),
+    Custom(Vec<f64>),
+}
+
+impl BinCurve {
+    /// Fills `values` with the multiplier of each bin.
+    fn fill(&self, values: &mut Vec<f64>, bins: usize) {
+        let half = bins / 2;
+        let mut rng = match self {
+            BinCurve::Random(seed) => Some(Rng::new(*seed)),
+            _ => None,
+        };
+        values.clear();
+        for j in 0..=half {
+            let position = (j as f64) / (half.max(1) as f64);
+            values.push(match self {
+                BinCurve::Flat => 1.0,
+                BinCurve::Rising => position,
+                BinCurve::Falling => 1.0 - position,
+                BinCurve::Random(_) => rng.as_mut().map_or(0.0, |rng| rng.next_f64()),
+                BinCurve::Custom(curve) => sample_curve(curve, position).clamp(0.0, 1.0),
+            });
+        }
+        for j in half + 1..bins {
+            values.push(values[mirrored_bin(bins, j)]);
+        }
+    }
+}
+
+/// ## Spectral Delay
+///
+/// Delays each bin by its own time and feeds it back by its own amount. Delay is measured in frames that are bins/time-div/sample-rate seconds long.
+/// A plugin can be placed in the feedback path to process the spectrum on every repeat, e.g. a [`FreqShifter`](crate::FreqShifter) for shimmer.
+///
+/// - Delay: longest delay in frames [1.0, 1999.0]
+/// - Delay curve: delay of each bin relative to Delay
+/// - Feedback: largest feedback multiplier [0.0, 1.0]
+/// - Feedback curve: feedback of each bin relative to Feedback
+/// - Mix: mixer for delayed/original signal [0.0, 1.0]
+/// - Feedback plugin: plugin processing the fed back spectrum
+pub struct SpectralDelay {
+    buffer: Vec<Vec<Vec<Bin>>>,
+    delayed: Vec<Vec<Bin>>,
+    feedback_in: Vec<Vec<Bin>>,
+    feedback_out: Vec<Vec<Bin>>,
+    delays: Vec<f64>,
+    feedbacks: Vec<f64>,
+    time: usize,
+    pub delay: ChannelParam,
+    pub delay_curve: BinCurve,
+    pub feedback: ChannelParam,
+    pub feedback_curve: BinCurve,
+    pub mix: ChannelParam,
+    pub feedback_plugin: Option<Box<dyn PVocMiniPlugin>>,
+}
+
+impl SpectralDelay {
+    const MAX_DELAY: usize = 2000;
+
+    pub fn new(channels: usize, bins: usize, delay: f64, feedback: f64, mix: f64) -> Self {
+        Self {
+            buffer: vec![vec![vec![Bin::new(0.0, 0.0); bins]; channels]; Self::MAX_DELAY],
+            delayed: vec![vec![Bin::new(0.0, 0.0); bins]; channels],
+            feedback_in: vec![vec![Bin::new(0.0, 0.0); bins]; channels],
+            feedback_out: vec![vec![Bin::new(0.0, 0.0); bins]; channels],
+            delays: Vec::with_capacity(bins),
+            feedbacks: Vec::with_capacity(bins),
+            time: 0,
+            delay: delay.into(),
+            delay_curve: BinCurve::Flat,
+            feedback: feedback.into(),
+            feedback_curve: BinCurve::Flat,
+            mix: mix.into(),
+            feedback_plugin: None,
+        }
+    }
+}
+
+impl PVocMiniPlugin for SpectralDelay {
+    fn process(
+        &mut self,
+        sample_rate: f64,
+        channels: usize,
+        bins: usize,
+        input: &[Vec<Bin>],
+        output: &mut [Vec<Bin>],
+    ) {
+        self.delay_curve.fill(&mut self.delays, bins);
+        self.feedback_curve.fill(&mut self.feedbacks, bins);
+
+        self.time %= Self::MAX_DELAY;
+        for i in 0..channels {
+            let delay = self.delay.get(i).clamp(1.0, (Self::MAX_DELAY - 1) as f64);
+            let feedback = self.feedback.get(i).clamp(0.0, 1.0);
+            for j in 0..bins {
+                let bin_delay = ((self.delays[j] * delay).round() as usize).max(1);
+                let frame = (self.time + Self::MAX_DELAY - bin_delay) % Self::MAX_DELAY;
+                let delayed = self.buffer[frame][i][j];
+                self.delayed[i][j] = delayed;
+                self.feedback_in[i][j] =
+                    Bin::new(delayed.freq, delayed.amp * self.feedbacks[j] * feedback);
+            }
+        }
+
+        match &mut self.feedback_plugin {
+            Some(plugin) => {
+                // like the phase vocoder, hand plugins an empty output
+                clear(&mut self.feedback_out, channels, bins);
+                plugin.process(
+                    sample_rate,
+                    channels,
+                    bins,
+                    &self.feedback_in,
+                    &mut self.feedback_out,
+                );
+            }
+            None => {
+                for i in 0..channels {
+                    self.feedback_out[i][..bins].copy_from_slice(&self.feedback_in[i][..bins]);
+                }
+            }
+        }
+
+        for i in 0..channels {
+            let mix = self.mix.get(i).clamp(0.0, 1.0);
+            for j in 0..bins {
+                let fed_back = self.feedback_out[i][j];
+                let written = &mut self.buffer[self.time][i][j];
+                written.amp = input[i][j].amp + fed_back.amp;
+                written.freq = if input[i][j].amp >= fed_back.amp {
+                    input[i][j].freq
+                } else {
+                    fed_back.freq
+                };
+
+                let wet = self.delayed[i][j].amp * mix;
+                let dry = input[i][j].amp * (1.0 - mix);
+                output[i][j].amp = lerp(self.delayed[i][j].amp, input[i][j].amp, mix);
+                output[i][j].freq = if dry >= wet {
+                    input[i][j].freq
+                } else {
+                    self.delayed[i][j].freq
+                };
+            }
+        }
+        self.time += 1;
+    }
+}
@@ -0,0 +1,99 @@
+use super::bin_freq;
+use super::smoothstep;
+use super::ChannelParam;
+use super::PVocMiniPlugin;
+use pvoc::Bin;
+use std::f64::consts::PI;
+
+/// A band of the [`SpectralEq`]. Frequencies are in Hz, gains in dB [-48.0, 48.0] and widths in octaves.
+#[derive(Clone, Debug)]
+pub enum EqBand {
+    LowShelf { freq: f64, gain: f64 },
+    HighShelf { freq: f64, gain: f64 },
+    Bell { freq: f64, gain: f64, width: f64 },
+    BandPass { low: f64, high: f64 },
+    BandStop { low: f64, high: f64 },
+}
+
+impl EqBand {
+    /// Linear gain of the band at `freq`, with shelf and brickwall edges spread over `edge` octaves.
+    /// Non-positive corners are below every bin, and the DC bin is below every positive corner.
+    fn gain(&self, freq: f64, edge: f64) -> f64 {
+        let step = |corner: f64| {
+            if corner <= 0.0 {
+                1.0
+            } else if freq <= 0.0 {
+                0.0
+            } else {
+                smoothstep(-edge / 2.0, edge / 2.0, (freq / corner).log2())
+            }
+        };
+        let db_to_amp = |db: f64| 10f64.powf(db.clamp(-48.0, 48.0) / 20.0);
+        match *self {
+            EqBand::LowShelf { freq: corner, gain } => db_to_amp(gain * (1.0 - step(corner))),
+            EqBand::HighShelf { freq: corner, gain } => db_to_amp(gain * step(corner)),
+            EqBand::Bell {
+                freq: centre,
+                gain,
+                width,
+            } => {
+                if centre <= 0.0 || freq <= 0.0 {
+                    return 1.0;
+                }
+                let octaves = (freq / centre).log2();
+                if octaves.abs() < width / 2.0 {
+                    db_to_amp(gain * 0.5 * (1.0 + (2.0 * PI * octaves / width).cos()))
+                } else {
+                    1.0
+                }
+            }
+            EqBand::BandPass { low, high } => step(low) * (1.0 - step(high)),
+            EqBand::BandStop { low, high } => 1.0 - step(low) * (1.0 - step(high)),
+        }
+    }
+}
+
+/// ## Spectral EQ
+///
+/// Boosts or attenuates frequency ranges given in Hz. The gains of all bands are multiplied together.
+///
+/// - Bands: the bands to apply
+/// - Edge: width of the transition at shelf and band edges in octaves [0.0, 4.0]
+pub struct SpectralEq {
+    pub bands: Vec<EqBand>,
+    pub edge: ChannelParam,
+}
+
+impl SpectralEq {
+    pub fn new(bands: Vec<EqBand>, edge: f64) -> Self {
+        Self {
+            bands,
+            edge: edge.into(),
+        }
+    }
+}
+
+impl PVocMiniPlugin for SpectralEq {
+    fn process(
+        &mut self,
+        sample_rate: f64,
+        channels: usize,
+        bins: usize,
+        input: &[Vec<Bin>],
+        output: &mut [Vec<Bin>],
+    ) {
+        for i in 0..channels {
+            let edge = self.edge.get(i).clamp(0.0, 4.0);
+            for j in 0..bins {
+                let freq = bin_freq(sample_rate, bins, j);
+                let gain: f64 = self
+                    .bands
+                    .iter()
+                    .map(|band| band.gain(freq, edge))
+                    .product();
+                output[i][j].amp = input[i][j].amp * gain;
+                output[i][j].freq = input[i][j].freq;
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use super::amp_to_dbfs;
+use super::mirrored_bin;
+use super::PVocMiniPlugin;
+use super::Rng;
+use pvoc::Bin;
+use std::f64::consts::FRAC_PI_2;
+
+/// How the [`SpectralPanner`] places each bin, from -1.0 (first channel) to 1.0 (last channel).
+///
+/// - Frequency: from the lowest bin to nyquist over octaves
+/// - Amplitude: from -96 dBFS to 0 dBFS
+/// - Random: a random position per bin, the same seed always gives the same positions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PanMode {
+    Frequency,
+    Amplitude,
+    Random(u64),
+}
+
+/// Gains of the two channels a bin is panned between.
+///
+/// - Linear: gains sum to 1.0, the centre is 6 dB down
+/// - ConstantPower: squared gains sum to 1.0, the centre is 3 dB down
+/// - Compromise: in between the two, the centre is 4.5 dB down
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PanLaw {
+    Linear,
+    ConstantPower,
+    Compromise,
+}
+
+impl PanLaw {
+    /// Gains of the channels on either side of position `x` [0.0, 1.0] between them.
+    fn gains(&self, x: f64) -> (f64, f64) {
+        let linear = (1.0 - x, x);
+        let power = ((x * FRAC_PI_2).cos(), (x * FRAC_PI_2).sin());
+        match self {
+            PanLaw::Linear => linear,
+            PanLaw::ConstantPower => power,
+            PanLaw::Compromise => ((linear.0 * power.0).sqrt(), (linear.1 * power.1).sqrt()),
+        }
+    }
+}
+
+/// ## Spectral Panner
+///
+/// Mixes the input channels down and spreads the bins across the output channels by frequency, amplitude or at random.
+///
+/// - Mode: what the position of each bin is derived from
+/// - Width: how far the bins are spread from Pan [0.0, 1.0]
+/// - Pan: centre of the spread [-1.0, 1.0]
+/// - Law: gains of the channels a bin is panned between
+pub struct SpectralPanner {
+    pub mode: PanMode,
+    pub width: f64,
+    pub pan: f64,
+    pub law: PanLaw,
+}
+
+impl SpectralPanner {
+    pub fn new(mode: PanMode, width: f64, pan: f64) -> Self {
+        Self {
+            mode,
+            width,
+            pan,
+            law: PanLaw::ConstantPower,
+        }
+    }
+}
+
+impl PVocMiniPlugin for SpectralPanner {
+    fn process(
+        &mut self,
+        _sample_rate: f64,
+        channels: usize,
+        bins: usize,
+        input: &[Vec<Bin>],
+        output: &mut [Vec<Bin>],
+    ) {
+        let width = self.width.clamp(0.0, 1.0);
+        let pan = self.pan.clamp(-1.0, 1.0);
+        let octaves = ((bins / 2).max(2) as f64).log2();
+        let random: Vec<f64> = match self.mode {
+            PanMode::Random(seed) => {
+                let mut rng = Rng::new(seed);
+                (0..=bins / 2).map(|_| rng.next_f64()).collect()
+            }
+            _ => Vec::new(),
+        };
+
+        for j in 0..bins {
+            let amp = input[..channels]
+                .iter()
+                .map(|channel| channel[j].amp)
+                .sum::<f64>()
+                / (channels as f64);
+            let freq = input[..channels]
+                .iter()
+                .max_by(|a, b| a[j].amp.total_cmp(&b[j].amp))
+                .map_or(0.0, |channel| channel[j].freq);
+
+            let index = mirrored_bin(bins, j);
+            let position = match self.mode {
+                PanMode::Frequency => (index.max(1) as f64).log2() / octaves,
+                PanMode::Amplitude => (amp_to_dbfs(amp, bins) + 96.0) / 96.0,
+                PanMode::Random(_) => random[index],
+            };
+            let position = (pan + width * (position.clamp(0.0, 1.0) * 2.0 - 1.0)).clamp(-1.0, 1.0);
+
+            for channel in output[..channels].iter_mut() {
+                channel[j] = Bin::new(freq, 0.0);
+            }
+            let x = (position + 1.0) / 2.0 * ((channels - 1) as f64);
+            let low = (x.floor() as usize).min(channels - 1);
+            let high = (low + 1).min(channels - 1);
+            let (low_gain, high_gain) = if low == high {
+                (1.0, 0.0)
+            } else {
+                self.law.gains(x - low as f64)
+            };
+            output[low][j].amp += amp * low_gain;
+            output[high][j].amp += amp * high_gain;
+        }
+    }
+}
@@ -1,20 +1,98 @@
+use super::clear;
 use super::PVocMiniPlugin;
 use pvoc::Bin;
 
+/// Gain compensation of the [`Stencil`] for the bins summed into each output bin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StencilNormalization {
+    None,
+    Count,
+    WeightSum,
+}
+
+/// Time-frequency kernel of the [`Stencil`].
+///
+/// `weights[y][x]` is applied to the bin `x - centre` bins away from the current one, `y` frames ago.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Kernel {
+    weights: Vec<Vec<f64>>,
+    centre: usize,
+}
+
+impl Kernel {
+    /// Returns `None` if `weights` is empty or ragged, or if `centre` is not a column of it.
+    pub fn new(weights: Vec<Vec<f64>>, centre: usize) -> Option<Self> {
+        let width = weights.first()?.len();
+        if centre >= width || weights.iter().any(|row| row.len() != width) {
+            return None;
+        }
+        Some(Self { weights, centre })
+    }
+
+    /// Builds the 4x4 binary kernel where bit `x * 4 + y` selects the bin `x - 2` bins away, `y` frames ago.
+    pub fn from_bits(bits: usize) -> Self {
+        const SIZE: usize = 4;
+        let weights = (0..SIZE)
+            .map(|y| {
+                (0..SIZE)
+                    .map(|x| ((bits >> (x * SIZE + y)) & 1) as f64)
+                    .collect()
+            })
+            .collect();
+        Self { weights, centre: 2 }
+    }
+
+    /// Parses a kernel with one row per frame separated by newlines or `;`, starting with the current frame.
+    /// Weights are separated by whitespace, `.` is 0.0 and `#` is 1.0, e.g. `"1 2 1; 1 2 1"` blurs and `"-1 2 -1"` sharpens.
+    pub fn from_pattern(pattern: &str, centre: usize) -> Option<Self> {
+        let weights = pattern
+            .split(['\n', ';'])
+            .filter(|row| !row.trim().is_empty())
+            .map(|row| {
+                row.split_whitespace()
+                    .map(|weight| match weight {
+                        "." => Some(0.0),
+                        "#" => Some(1.0),
+                        _ => weight.parse().ok(),
+                    })
+                    .collect::<Option<Vec<f64>>>()
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Self::new(weights, centre)
+    }
+
+    /// Number of frames the kernel spans.
+    pub fn frames(&self) -> usize {
+        self.weights.len()
+    }
+}
+
+/// ## Stencil
+///
+/// Time-frequency convolution: each bin is the weighted sum of its neighbours in time and frequency given by a kernel.
+/// Borrowed frequencies are moved by the bin difference so they stay relative to the destination bin.
+/// Amplitudes that end up negative are silenced.
+///
+/// - Kernel: the weights, either a 4x4 binary mask [0, 65535] or any [`Kernel`]
+/// - Normalization: amplitude compensation for the contributing bins, frequencies are always averaged
 pub struct Stencil {
     buffer: Vec<Vec<Vec<Bin>>>,
     time: usize,
-    pub stencil: usize,
+    pub kernel: Kernel,
+    pub normalization: StencilNormalization,
 }
 
 impl Stencil {
-    const SIZE: usize = 4;
-
     pub fn new(channels: usize, bins: usize, stencil: usize) -> Self {
+        Self::with_kernel(channels, bins, Kernel::from_bits(stencil))
+    }
+
+    pub fn with_kernel(channels: usize, bins: usize, kernel: Kernel) -> Self {
         Self {
-            buffer: vec![vec![vec![Bin::new(0.0, 0.0); bins]; channels]; Self::SIZE],
+            buffer: vec![vec![vec![Bin::new(0.0, 0.0); bins]; channels]; kernel.frames()],
             time: 0,
-            stencil,
+            kernel,
+            normalization: StencilNormalization::Count,
         }
     }
 }
@@ -28,37 +106,49 @@ impl PVocMiniPlugin for Stencil {
         input: &[Vec<Bin>],
         output: &mut [Vec<Bin>],
     ) {
-        let stencil = self.stencil.clamp(0, (1 << Self::SIZE * Self::SIZE) - 1);
-        let _freq_per_bin = sample_rate / (bins as f64);
-        self.time %= Self::SIZE;
+        let frames = self.kernel.frames();
+        let centre = self.kernel.centre;
+        let freq_per_bin = sample_rate / (bins as f64);
+
+        if self.buffer.len() != frames {
+            self.buffer
+                .resize(frames, vec![vec![Bin::new(0.0, 0.0); bins]; channels]);
+        }
+        self.time %= frames;
+
+        clear(output, channels, bins);
         for i in 0..channels {
-            for j in 0..bins {
-                self.buffer[self.time][i][j] = input[i][j];
-            }
-            for j in 0..bins {
-                let mut tmp = stencil;
+            self.buffer[self.time][i][..bins].copy_from_slice(&input[i][..bins]);
+            for (j, out) in output[i].iter_mut().enumerate().take(bins) {
                 let mut ncontrib = 0;
-                for x in 0..Self::SIZE {
-                    for y in 0..Self::SIZE {
-                        if tmp & 1 == 1 {
-                            let bin = j + x;
-                            if bin < 2 || bin >= bins - 2 {
-                                continue;
-                            }
-                            let bin = bin - 2;
-                            let frame = (self.time + Self::SIZE - y) % Self::SIZE;
-                            output[i][j].amp += self.buffer[frame][i][bin].amp;
-                            // TODO adjust frequency by bin difference
-                            output[i][j].freq += self.buffer[frame][i][bin].freq;
-                            ncontrib += 1;
-                        }
-                        tmp >>= 1;
+                let mut weight_sum = 0.0;
+                let mut abs_weight_sum = 0.0;
+                for (y, row) in self.kernel.weights.iter().enumerate() {
+                    let frame = (self.time + frames - y) % frames;
+                    for (x, &weight) in row.iter().enumerate() {
+                        let bin = match (j + x).checked_sub(centre) {
+                            Some(bin) if bin < bins && weight != 0.0 => bin,
+                            _ => continue,
+                        };
+                        let offset = (j as f64 - bin as f64) * freq_per_bin;
+                        out.amp += weight * self.buffer[frame][i][bin].amp;
+                        out.freq += weight.abs() * (self.buffer[frame][i][bin].freq + offset);
+                        ncontrib += 1;
+                        weight_sum += weight;
+                        abs_weight_sum += weight.abs();
                     }
                 }
                 if ncontrib > 0 {
-                    // TODO configurable gain compensation
-                    output[i][j].amp /= ncontrib as f64;
-                    output[i][j].freq /= ncontrib as f64;
+                    out.freq /= abs_weight_sum;
+                    match self.normalization {
+                        StencilNormalization::None => {}
+                        StencilNormalization::Count => out.amp /= ncontrib as f64,
+                        StencilNormalization::WeightSum if weight_sum != 0.0 => {
+                            out.amp /= weight_sum.abs()
+                        }
+                        StencilNormalization::WeightSum => {}
+                    }
+                    out.amp = out.amp.max(0.0);
                 }
             }
         }
@@ -0,0 +1,29 @@
+use super::frame_seconds;
+
+/// A length in beats that stays in sync with the tempo.
+/// It is converted to frames from the sample rate and the FFT settings every time it is used.
+///
+/// - BPM: tempo in beats per minute [20.0, 400.0]
+/// - Beats: length in beats, where a beat is a quarter note [0.0, 64.0]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TempoSync {
+    pub bpm: f64,
+    pub beats: f64,
+}
+
+impl TempoSync {
+    pub fn new(bpm: f64, beats: f64) -> Self {
+        Self { bpm, beats }
+    }
+
+    /// Length of a 1/`division` note, e.g. 8.0 for an eighth note or 12.0 for an eighth note triplet.
+    pub fn note(bpm: f64, division: f64) -> Self {
+        Self::new(bpm, 4.0 / division)
+    }
+
+    /// Length in frames, where `time_div` is the number of overlapping frames of the phase vocoder.
+    pub fn frames(&self, sample_rate: f64, bins: usize, time_div: usize) -> f64 {
+        let seconds = self.beats.clamp(0.0, 64.0) * 60.0 / self.bpm.clamp(20.0, 400.0);
+        seconds / frame_seconds(sample_rate, bins, time_div)
+    }
+}
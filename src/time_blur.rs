@@ -1,7 +1,20 @@
+use super::frame_seconds;
 use super::lerp;
+use super::time_constant;
+use super::ChannelParam;
 use super::PVocMiniPlugin;
 use pvoc::Bin;
 
+/// How the averaging of the [`TimeBlur`] is specified.
+///
+/// - Raw: the alphas and the replace mixers are per-frame coefficients
+/// - Milliseconds: time constants for frequency [0.0, 10000.0] and for rising [0.0, 10000.0] and falling [0.0, 10000.0] amplitude, converted to per-frame coefficients from the hop size
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlurTime {
+    Raw,
+    Milliseconds { freq: f64, rise: f64, fall: f64 },
+}
+
 /// ## Time Blur
 ///
 /// Uses exponential averaging to blur amplitude and frequency across time.
@@ -12,20 +25,26 @@ use pvoc::Bin;
 /// - Amplitude mix: Mixer for original/modulated amplitude [0.0, 1.0]
 /// - Amplitude high replace: Mixer for replacing blurred amplitude with current amplitude when current amplitude exceeds blurred amplitude. [0.0, 1.0]
 /// - Amplitude low replace: Mixer for replacing blurred amplitude with current amplitude when blurred amplitude exceeds current amplitude. [0.0, 1.0]
+/// - Time: raw coefficients, or time constants in milliseconds that replace the alphas and the replace mixers
+/// - Time divs: the number of overlapping frames of the phase vocoder, used to convert milliseconds to frames
 pub struct TimeBlur {
     buffer: Vec<Vec<Bin>>,
-    pub freq_alpha: f64,
-    pub amp_alpha: f64,
-    pub freq_mix: f64,
-    pub amp_mix: f64,
-    pub replace_high: f64,
-    pub replace_low: f64,
+    pub time_div: usize,
+    pub freq_alpha: ChannelParam,
+    pub amp_alpha: ChannelParam,
+    pub freq_mix: ChannelParam,
+    pub amp_mix: ChannelParam,
+    pub replace_high: ChannelParam,
+    pub replace_low: ChannelParam,
+    pub time: BlurTime,
 }
 
 impl TimeBlur {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         channels: usize,
         bins: usize,
+        time_div: usize,
         freq_alpha: f64,
         amp_alpha: f64,
         freq_mix: f64,
@@ -35,12 +54,14 @@ impl TimeBlur {
     ) -> Self {
         Self {
             buffer: vec![vec![Bin::new(0.0, 0.0); bins]; channels],
-            freq_alpha,
-            amp_alpha,
-            freq_mix,
-            amp_mix,
-            replace_high,
-            replace_low,
+            time_div,
+            freq_alpha: freq_alpha.into(),
+            amp_alpha: amp_alpha.into(),
+            freq_mix: freq_mix.into(),
+            amp_mix: amp_mix.into(),
+            replace_high: replace_high.into(),
+            replace_low: replace_low.into(),
+            time: BlurTime::Raw,
         }
     }
 }
@@ -48,20 +69,44 @@ impl TimeBlur {
 impl PVocMiniPlugin for TimeBlur {
     fn process(
         &mut self,
-        _sample_rate: f64,
+        sample_rate: f64,
         channels: usize,
         bins: usize,
         input: &[Vec<Bin>],
         output: &mut [Vec<Bin>],
     ) {
-        let freq_alpha = self.freq_alpha.clamp(0.0, 1.0);
-        let amp_alpha = self.amp_alpha.clamp(0.0, 1.0);
-        let freq_mix = self.freq_mix.clamp(0.0, 1.0);
-        let amp_mix = self.amp_mix.clamp(0.0, 1.0);
-        let replace_high = self.replace_high.clamp(0.0, 1.0);
-        let replace_low = self.replace_low.clamp(0.0, 1.0);
         let buffer = &mut self.buffer;
+
+        if let BlurTime::Milliseconds { freq, rise, fall } = self.time {
+            let hop = frame_seconds(sample_rate, bins, self.time_div);
+            let freq_alpha = time_constant(freq.clamp(0.0, 10000.0), hop);
+            let rise_alpha = time_constant(rise.clamp(0.0, 10000.0), hop);
+            let fall_alpha = time_constant(fall.clamp(0.0, 10000.0), hop);
+            for i in 0..channels {
+                let freq_mix = self.freq_mix.get(i).clamp(0.0, 1.0);
+                let amp_mix = self.amp_mix.get(i).clamp(0.0, 1.0);
+                for j in 0..bins {
+                    let amp_alpha = if input[i][j].amp > buffer[i][j].amp {
+                        rise_alpha
+                    } else {
+                        fall_alpha
+                    };
+                    buffer[i][j].freq = lerp(buffer[i][j].freq, input[i][j].freq, freq_alpha);
+                    buffer[i][j].amp = lerp(buffer[i][j].amp, input[i][j].amp, amp_alpha);
+                    output[i][j].freq = lerp(buffer[i][j].freq, input[i][j].freq, freq_mix);
+                    output[i][j].amp = lerp(buffer[i][j].amp, input[i][j].amp, amp_mix);
+                }
+            }
+            return;
+        }
+
         for i in 0..channels {
+            let freq_alpha = self.freq_alpha.get(i).clamp(0.0, 1.0);
+            let amp_alpha = self.amp_alpha.get(i).clamp(0.0, 1.0);
+            let freq_mix = self.freq_mix.get(i).clamp(0.0, 1.0);
+            let amp_mix = self.amp_mix.get(i).clamp(0.0, 1.0);
+            let replace_high = self.replace_high.get(i).clamp(0.0, 1.0);
+            let replace_low = self.replace_low.get(i).clamp(0.0, 1.0);
             for j in 0..bins {
                 buffer[i][j].freq = lerp(buffer[i][j].freq, input[i][j].freq, freq_alpha);
                 buffer[i][j].amp = lerp(buffer[i][j].amp, input[i][j].amp, amp_alpha);
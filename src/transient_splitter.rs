@@ -0,0 +1,105 @@
+use super::ChannelParam;
+use super::PVocMiniPlugin;
+use pvoc::Bin;
+
+/// ## Transient Splitter
+///
+/// Classifies each bin as tonal, transient or noise by median filtering the amplitudes across time and frequency, then remixes the three components.
+/// A bin is tonal when its median across time exceeds its median across frequency by the separation factor, transient in the opposite case and noise otherwise.
+/// Set two of the gains to 0.0 to output a single component.
+///
+/// - Time size: number of frames for the median across time [1, 64]
+/// - Frequency size: number of bins for the median across frequency [1, 64]
+/// - Separation: factor one median has to exceed the other by [1.0, 8.0]
+/// - Tonal/transient/noise: gain of each component [0.0, 2.0]
+pub struct TransientSplitter {
+    buffer: Vec<Vec<Vec<f64>>>,
+    scratch: Vec<f64>,
+    time: usize,
+    pub time_size: usize,
+    pub freq_size: usize,
+    pub separation: ChannelParam,
+    pub tonal: ChannelParam,
+    pub transient: ChannelParam,
+    pub noise: ChannelParam,
+}
+
+impl TransientSplitter {
+    const MAX_SIZE: usize = 64;
+
+    pub fn new(
+        channels: usize,
+        bins: usize,
+        time_size: usize,
+        freq_size: usize,
+        separation: f64,
+    ) -> Self {
+        Self {
+            buffer: vec![vec![vec![0.0; bins]; channels]; Self::MAX_SIZE],
+            scratch: Vec::with_capacity(Self::MAX_SIZE),
+            time: 0,
+            time_size,
+            freq_size,
+            separation: separation.into(),
+            tonal: 1.0.into(),
+            transient: 1.0.into(),
+            noise: 1.0.into(),
+        }
+    }
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    let mid = values.len() / 2;
+    *values.select_nth_unstable_by(mid, |a, b| a.total_cmp(b)).1
+}
+
+impl PVocMiniPlugin for TransientSplitter {
+    fn process(
+        &mut self,
+        _sample_rate: f64,
+        channels: usize,
+        bins: usize,
+        input: &[Vec<Bin>],
+        output: &mut [Vec<Bin>],
+    ) {
+        let time_size = self.time_size.clamp(1, Self::MAX_SIZE);
+        let freq_size = self.freq_size.clamp(1, Self::MAX_SIZE);
+
+        self.time %= Self::MAX_SIZE;
+        for i in 0..channels {
+            let separation = self.separation.get(i).clamp(1.0, 8.0);
+            let tonal = self.tonal.get(i).clamp(0.0, 2.0);
+            let transient = self.transient.get(i).clamp(0.0, 2.0);
+            let noise = self.noise.get(i).clamp(0.0, 2.0);
+            for (amp, bin) in self.buffer[self.time][i].iter_mut().zip(&input[i]) {
+                *amp = bin.amp;
+            }
+            for j in 0..bins {
+                self.scratch.clear();
+                for t in 0..time_size {
+                    let frame = (self.time + Self::MAX_SIZE - t) % Self::MAX_SIZE;
+                    self.scratch.push(self.buffer[frame][i][j]);
+                }
+                let across_time = median(&mut self.scratch);
+
+                let low = j.saturating_sub(freq_size / 2);
+                let high = (low + freq_size).min(bins);
+                self.scratch.clear();
+                self.scratch
+                    .extend(input[i][low..high].iter().map(|bin| bin.amp));
+                let across_freq = median(&mut self.scratch);
+
+                let gain = if across_time > across_freq * separation {
+                    tonal
+                } else if across_freq > across_time * separation {
+                    transient
+                } else {
+                    noise
+                };
+                output[i][j].amp = input[i][j].amp * gain;
+                output[i][j].freq = input[i][j].freq;
+            }
+        }
+        self.time += 1;
+    }
+}
@@ -0,0 +1,47 @@
+use super::lerp;
+use super::ChannelParam;
+use super::PVocMiniPlugin;
+use super::Rng;
+use pvoc::Bin;
+
+/// ## Whisperize
+///
+/// Randomizes the frequency of each bin within the bin while keeping its amplitude.
+/// The same seed always produces the same output.
+///
+/// - Amount: Mixer for random/original frequency [0.0, 1.0]
+/// - Seed: seed of the random generator
+pub struct Whisperize {
+    rng: Rng,
+    pub amount: ChannelParam,
+}
+
+impl Whisperize {
+    pub fn new(amount: f64, seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            amount: amount.into(),
+        }
+    }
+}
+
+impl PVocMiniPlugin for Whisperize {
+    fn process(
+        &mut self,
+        sample_rate: f64,
+        channels: usize,
+        bins: usize,
+        input: &[Vec<Bin>],
+        output: &mut [Vec<Bin>],
+    ) {
+        let freq_per_bin = sample_rate / (bins as f64);
+        for i in 0..channels {
+            let amount = self.amount.get(i).clamp(0.0, 1.0);
+            for j in 0..bins {
+                let random = freq_per_bin * ((j as f64) + self.rng.next_f64());
+                output[i][j].amp = input[i][j].amp;
+                output[i][j].freq = lerp(random, input[i][j].freq, amount);
+            }
+        }
+    }
+}